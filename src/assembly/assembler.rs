@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::assembly::exceptions::AssemblyError;
+use crate::byte_readable::ByteOrder;
+use crate::code::{Chunk, Code};
+use crate::exception::Exception;
+use crate::instruction_table::InstructionTable;
+use crate::parsing::exceptions::EmptyCode;
+
+/// Builds `Code` from a textual program.
+///
+/// ```text
+/// ; comments start with a semicolon
+/// .chunk main          ; starts a chunk, the name is optional
+/// .const 42            ; adds a constant, the text is parsed by the constant parser
+/// loop:                ; defines a label at the offset of the next instruction
+///     PUSH 0           ; an instruction by its `name` and its arguments
+///     JUMP_BACK <loop  ; labels and chunks can be used as arguments
+/// ```
+///
+/// Arguments are separated by whitespace or commas. An argument is either a number
+/// (`42`, `-1`, `0x2a`), `@label` -- the offset of the label in the chunk,
+/// `>label` and `<label` -- the distance from the end of the instruction forward or backward to the label,
+/// or `#chunk` -- the id of the named chunk.
+///
+/// An instruction accepts `byte_arity` arguments of one byte each
+/// or a single argument that is encoded in `byte_arity` bytes in the byte order of the assembler.
+pub struct Assembler<'a, Constant, Value: Debug> {
+    instruction_table: &'a InstructionTable<'a, Constant, Value>,
+    constant_parser: ConstantLiteralParser<'a, Constant>,
+    byte_order: ByteOrder,
+}
+
+/// Parses the text of a `.const` directive
+type ConstantLiteralParser<'a, Constant> = Box<dyn Fn(&str) -> Result<Constant, String> + 'a>;
+
+/// A chunk after the first pass, where labels are collected but arguments are not resolved
+struct ChunkSource<'s, Constant> {
+    constants: Vec<Constant>,
+    instructions: Vec<InstructionSource<'s>>,
+    labels: HashMap<&'s str, usize>,
+    size: usize,
+}
+
+struct InstructionSource<'s> {
+    line: usize,
+    name: &'s str,
+    op_code: u8,
+    byte_arity: usize,
+    arguments: Vec<&'s str>,
+}
+
+impl<'a, Constant, Value: Debug> Assembler<'a, Constant, Value> {
+    pub fn new(
+        instruction_table: &'a InstructionTable<'a, Constant, Value>,
+        constant_parser: impl Fn(&str) -> Result<Constant, String> + 'a,
+    ) -> Assembler<'a, Constant, Value> {
+        Assembler {
+            instruction_table,
+            constant_parser: Box::new(constant_parser),
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    pub fn with_byte_order(self, byte_order: ByteOrder) -> Assembler<'a, Constant, Value> {
+        Assembler { byte_order, ..self }
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Code<'static, Constant>, Exception> {
+        let mut chunk_names: HashMap<&str, usize> = HashMap::new();
+        let mut sources: Vec<ChunkSource<Constant>> = vec![];
+        for (index, line) in source.lines().enumerate() {
+            self.read_line(index + 1, line, &mut sources, &mut chunk_names)?;
+        }
+        if sources.is_empty() {
+            return Err(Exception::from(EmptyCode));
+        }
+        let mut chunks = vec![];
+        for source in sources {
+            let mut code = Vec::with_capacity(source.size);
+            for instruction in &source.instructions {
+                self.write_instruction(instruction, &source, &chunk_names, &mut code)
+                    .map_err(|message| AssemblyError {
+                        line: instruction.line,
+                        message,
+                    })?;
+            }
+            chunks.push(Chunk {
+                constants: source.constants,
+                code: code.into(),
+                exception_handlers: vec![],
+            });
+        }
+        Ok(Code { chunks })
+    }
+
+    fn read_line<'s>(
+        &self,
+        line_number: usize,
+        line: &'s str,
+        sources: &mut Vec<ChunkSource<'s, Constant>>,
+        chunk_names: &mut HashMap<&'s str, usize>,
+    ) -> Result<(), AssemblyError> {
+        let error = |message: String| AssemblyError {
+            line: line_number,
+            message,
+        };
+        let mut line = strip_comment(line).trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        if let Some(name) = directive(line, ".chunk") {
+            if !name.is_empty() && chunk_names.insert(name, sources.len()).is_some() {
+                return Err(error(format!("Chunk {} is already defined", name)));
+            }
+            sources.push(ChunkSource {
+                constants: vec![],
+                instructions: vec![],
+                labels: HashMap::new(),
+                size: 0,
+            });
+            return Ok(());
+        }
+        let chunk = sources
+            .last_mut()
+            .ok_or_else(|| error("Code must start with .chunk".to_string()))?;
+        if let Some(literal) = directive(line, ".const") {
+            let constant = (self.constant_parser)(literal)
+                .map_err(|message| error(format!("Invalid constant {}: {}", literal, message)))?;
+            chunk.constants.push(constant);
+            return Ok(());
+        }
+        if line.starts_with('.') {
+            return Err(error(format!("Unknown directive {}", line)));
+        }
+        while let Some((label, rest)) = split_label(line) {
+            if chunk.labels.insert(label, chunk.size).is_some() {
+                return Err(error(format!("Label {} is already defined", label)));
+            }
+            line = rest;
+        }
+        if line.is_empty() {
+            return Ok(());
+        }
+        let mut tokens = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty());
+        let name = tokens
+            .next()
+            .ok_or_else(|| error(format!("Expected an instruction, found {}", line)))?;
+        let instruction = self
+            .instruction_table
+            .get_instruction_by_name(name)
+            .ok_or_else(|| error(format!("Unknown instruction {}", name)))?;
+        let byte_arity = instruction.instruction_fn.byte_arity();
+        chunk.instructions.push(InstructionSource {
+            line: line_number,
+            name,
+            op_code: instruction.op_code,
+            byte_arity,
+            arguments: tokens.collect(),
+        });
+        chunk.size += 1 + byte_arity;
+        Ok(())
+    }
+
+    fn write_instruction(
+        &self,
+        instruction: &InstructionSource,
+        chunk: &ChunkSource<Constant>,
+        chunk_names: &HashMap<&str, usize>,
+        code: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        code.push(instruction.op_code);
+        let end = code.len() + instruction.byte_arity;
+        let values = instruction
+            .arguments
+            .iter()
+            .map(|argument| resolve_argument(argument, end, chunk, chunk_names))
+            .collect::<Result<Vec<i128>, String>>()?;
+        if values.len() == instruction.byte_arity {
+            for value in values {
+                code.extend(self.encode(value, 1)?);
+            }
+        } else if values.len() == 1 && instruction.byte_arity > 1 {
+            code.extend(self.encode(values[0], instruction.byte_arity)?);
+        } else {
+            return Err(format!(
+                "{} expects {} argument byte(s) but got {} argument(s)",
+                instruction.name,
+                instruction.byte_arity,
+                values.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encodes the value in `width` bytes, accepting both signed and unsigned values.
+    fn encode(&self, value: i128, width: usize) -> Result<Vec<u8>, String> {
+        let bits = 8 * width as u32;
+        let fits = bits >= 127 || (value >= -(1 << (bits - 1)) && value < (1 << bits));
+        if !fits {
+            return Err(format!("{} does not fit into {} byte(s)", value, width));
+        }
+        let sign = if value < 0 { 0xff } else { 0 };
+        let le_bytes = value.to_le_bytes();
+        let mut bytes: Vec<u8> = (0..width)
+            .map(|i| le_bytes.get(i).cloned().unwrap_or(sign))
+            .collect();
+        if self.byte_order == ByteOrder::BigEndian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Returns the rest of the line if it starts with the directive.
+fn directive<'s>(line: &'s str, name: &str) -> Option<&'s str> {
+    let rest = line.strip_prefix(name)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// Splits `label: rest` into the label and the rest of the line.
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let end = line.find(char::is_whitespace).unwrap_or(line.len());
+    let label = line[..end].strip_suffix(':')?;
+    if label.is_empty() {
+        return None;
+    }
+    Some((label, line[end..].trim_start()))
+}
+
+fn resolve_argument<Constant>(
+    argument: &str,
+    instruction_end: usize,
+    chunk: &ChunkSource<Constant>,
+    chunk_names: &HashMap<&str, usize>,
+) -> Result<i128, String> {
+    let label = |name: &str| {
+        chunk
+            .labels
+            .get(name)
+            .map(|offset| *offset as i128)
+            .ok_or_else(|| format!("Label {} is not defined", name))
+    };
+    let end = instruction_end as i128;
+    if let Some(name) = argument.strip_prefix('@') {
+        label(name)
+    } else if let Some(name) = argument.strip_prefix('>') {
+        let distance = label(name)? - end;
+        if distance < 0 {
+            return Err(format!("Label {} is not after the instruction", name));
+        }
+        Ok(distance)
+    } else if let Some(name) = argument.strip_prefix('<') {
+        let distance = end - label(name)?;
+        if distance < 0 {
+            return Err(format!("Label {} is not before the instruction", name));
+        }
+        Ok(distance)
+    } else if let Some(name) = argument.strip_prefix('#') {
+        chunk_names
+            .get(name)
+            .map(|chunk_id| *chunk_id as i128)
+            .ok_or_else(|| format!("Chunk {} is not defined", name))
+    } else {
+        parse_number(argument).ok_or_else(|| format!("Invalid argument {}", argument))
+    }
+}
+
+fn parse_number(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembly::assembler::Assembler;
+    use crate::byte_readable::ByteOrder;
+    use crate::code::Code;
+    use crate::exception::Exception;
+    use crate::instruction::Instruction;
+    use crate::instruction_table::InstructionTable;
+    use crate::test_support::{noop, raw, Constant, Value};
+
+    const PUSH: Instruction<Constant, Value> = raw(0, "PUSH", 1, noop);
+    const JUMP: Instruction<Constant, Value> = raw(1, "JUMP", 1, noop);
+    const LOOP: Instruction<Constant, Value> = raw(2, "LOOP", 1, noop);
+    const CALL: Instruction<Constant, Value> = raw(3, "CALL", 1, noop);
+    const LOAD: Instruction<Constant, Value> = raw(4, "LOAD", 2, noop);
+    const RET: Instruction<Constant, Value> = raw(5, "RET", 0, noop);
+
+    const INSTRUCTIONS: [&Instruction<Constant, Value>; 6] =
+        [&PUSH, &JUMP, &LOOP, &CALL, &LOAD, &RET];
+
+    fn parse_constant(text: &str) -> Result<Constant, String> {
+        text.parse().map_err(|_| "not an int".to_string())
+    }
+
+    fn assemble(source: &str) -> Result<Code<'static, Constant>, Exception> {
+        let table = InstructionTable::instructions(&INSTRUCTIONS);
+        let assembler = Assembler::new(&table, parse_constant);
+        assembler.assemble(source)
+    }
+
+    #[test]
+    fn program_should_be_assembled() {
+        let code = assemble(
+            "
+            .chunk main
+            .const 7       ; first constant
+            .const -1
+                PUSH 0
+            start: JUMP >end
+                CALL #f
+                LOOP <start
+            end:
+                RET
+
+            .chunk f
+                LOAD 0x1234
+                RET
+            ",
+        )
+        .unwrap();
+        assert_eq!(vec![7, -1], code.chunks[0].constants);
+        assert_eq!(&[0, 0, 1, 4, 3, 1, 2, 6, 5], code.chunks[0].code.as_ref());
+        assert_eq!(&[4, 0x34, 0x12, 5], code.chunks[1].code.as_ref());
+    }
+
+    #[test]
+    fn arguments_should_be_bytes_or_a_single_value() {
+        let code = assemble(".chunk\nLOAD 1, 2\nLOAD -1\nLOAD @end\nend:").unwrap();
+        assert_eq!(
+            &[4, 1, 2, 4, 0xff, 0xff, 4, 9, 0],
+            code.chunks[0].code.as_ref()
+        );
+    }
+
+    #[test]
+    fn values_should_use_byte_order() {
+        let table = InstructionTable::instructions(&INSTRUCTIONS);
+        let code = Assembler::new(&table, parse_constant)
+            .with_byte_order(ByteOrder::BigEndian)
+            .assemble(".chunk\nLOAD 0x1234")
+            .unwrap();
+        assert_eq!(&[4, 0x12, 0x34], code.chunks[0].code.as_ref());
+    }
+
+    #[test]
+    fn errors_should_point_to_the_line() {
+        let message = |source: &str| assemble(source).unwrap_err().message;
+        assert_eq!("Line 2: Unknown instruction POP", message(".chunk\nPOP"));
+        assert_eq!("Line 1: Code must start with .chunk", message("RET"));
+        assert_eq!(
+            "Line 3: Label nowhere is not defined",
+            message(".chunk\nRET\nJUMP >nowhere")
+        );
+        assert_eq!(
+            "Line 2: 256 does not fit into 1 byte(s)",
+            message(".chunk\nPUSH 256")
+        );
+        assert_eq!(
+            "Line 2: Invalid constant x: not an int",
+            message(".chunk\n.const x")
+        );
+        assert_eq!(
+            "Line 2: RET expects 0 argument byte(s) but got 1 argument(s)",
+            message(".chunk\nRET 1")
+        );
+        assert_eq!(
+            "Line 3: Expected an instruction, found ,",
+            message(".chunk\nRET\nend: ,")
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::{Debug, Write};
+
+use crate::code::{Chunk, Code};
+use crate::decoder::decode;
+use crate::instruction_table::InstructionTable;
+
+/// The width of the mnemonic column in listings
+const MNEMONIC_WIDTH: usize = 12;
+
+/// The column where comments start in listings
+const COMMENT_COLUMN: usize = 40;
+
+/// Renders a listing of every chunk of the code.
+///
+/// For each chunk the listing contains its constant pool followed by its instructions:
+/// the offset, the mnemonic and the raw bytes of the arguments in hex.
+/// Unknown opcodes and instructions truncated by the end of the chunk are flagged with a comment.
+///
+/// ```text
+/// == chunk #0 ==
+/// constants:
+///   #0   Int(7)
+/// code:
+///   0000  PUSH         00
+///   0002  UNKNOWN(42)                      ; unknown opcode
+///   0003  LOAD         34                  ; truncated: 1 of 2 argument bytes
+/// ```
+pub fn disassemble<Constant: Debug, Value: Debug>(
+    code: &Code<Constant>,
+    instruction_table: &InstructionTable<Constant, Value>,
+) -> String {
+    let mut listing = String::new();
+    for (chunk_id, chunk) in code.chunks.iter().enumerate() {
+        if chunk_id > 0 {
+            listing.push('\n');
+        }
+        listing.push_str(&disassemble_chunk(chunk_id, chunk, instruction_table));
+    }
+    listing
+}
+
+/// Renders a listing of one chunk, see `disassemble`.
+pub fn disassemble_chunk<Constant: Debug, Value: Debug>(
+    chunk_id: usize,
+    chunk: &Chunk<Constant>,
+    instruction_table: &InstructionTable<Constant, Value>,
+) -> String {
+    let mut listing = String::new();
+    writeln!(listing, "== chunk #{} ==", chunk_id).unwrap();
+    if !chunk.constants.is_empty() {
+        listing.push_str("constants:\n");
+        for (constant_id, constant) in chunk.constants.iter().enumerate() {
+            writeln!(listing, "  #{:<3} {:?}", constant_id, constant).unwrap();
+        }
+    }
+    listing.push_str("code:\n");
+    for instruction in decode(&chunk.code, instruction_table) {
+        let arguments: Vec<String> = instruction
+            .arguments
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let mut line = format!(
+            "  {:04x}  {:<width$} {}",
+            instruction.offset,
+            instruction.name(),
+            arguments.join(" "),
+            width = MNEMONIC_WIDTH
+        );
+        let comment = match instruction.instruction {
+            None => Some("unknown opcode".to_string()),
+            Some(definition) if instruction.truncated => Some(format!(
+                "truncated: {} of {} argument bytes",
+                instruction.arguments.len(),
+                definition.instruction_fn.byte_arity()
+            )),
+            Some(_) => None,
+        };
+        if let Some(comment) = comment {
+            line = format!("{:<width$} ; {}", line, comment, width = COMMENT_COLUMN);
+        }
+        listing.push_str(line.trim_end());
+        listing.push('\n');
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembly::disassembler::disassemble;
+    use crate::code::{Chunk, Code};
+    use crate::instruction::Instruction;
+    use crate::instruction_table::InstructionTable;
+    use crate::test_support::{noop, raw, Constant, Value};
+
+    const PUSH: Instruction<Constant, Value> = raw(0, "PUSH", 1, noop);
+    const LOAD: Instruction<Constant, Value> = raw(1, "LOAD", 2, noop);
+
+    const INSTRUCTIONS: [&Instruction<Constant, Value>; 2] = [&PUSH, &LOAD];
+
+    #[test]
+    fn listing_should_contain_constants_and_instructions() {
+        let code = Code {
+            chunks: vec![
+                Chunk {
+                    constants: vec![7, -1],
+                    code: vec![0, 5, 42, 1, 0x34].into(),
+                    exception_handlers: vec![],
+                },
+                Chunk {
+                    constants: vec![],
+                    code: vec![1, 0x34, 0x12].into(),
+                    exception_handlers: vec![],
+                },
+            ],
+        };
+        let table = InstructionTable::instructions(&INSTRUCTIONS);
+        assert_eq!(
+            "\
+== chunk #0 ==
+constants:
+  #0   7
+  #1   -1
+code:
+  0000  PUSH         05
+  0002  UNKNOWN(42)                      ; unknown opcode
+  0003  LOAD         34                  ; truncated: 1 of 2 argument bytes
+
+== chunk #1 ==
+code:
+  0000  LOAD         34 12
+",
+            disassemble(&code, &table)
+        );
+    }
+}
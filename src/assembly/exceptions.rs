@@ -0,0 +1,17 @@
+use crate::exception::{Exception, ExceptionType};
+
+/// The assembly source is invalid at the given line (starting from 1)
+pub struct AssemblyError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl From<AssemblyError> for Exception {
+    fn from(error: AssemblyError) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "AssemblyError".to_string(),
+            message: format!("Line {}: {}", error.line, error.message),
+        }
+    }
+}
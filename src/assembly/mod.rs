@@ -0,0 +1,6 @@
+pub use assembler::Assembler;
+pub use disassembler::{disassemble, disassemble_chunk};
+
+mod assembler;
+mod disassembler;
+pub mod exceptions;
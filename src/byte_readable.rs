@@ -1,7 +1,39 @@
+use std::convert::TryFrom;
+
+/// The order of bytes in multi-byte values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+/// Defines `read_<ty>` that uses `byte_order()` and `read_<ty>_le`, `read_<ty>_be`
+/// that use a fixed byte order.
+macro_rules! fixed_width_readers {
+    ($($ty:ident: $read:ident, $read_le:ident, $read_be:ident;)*) => {
+        $(
+            fn $read(&self, ptr: &mut Ptr) -> Option<$ty> {
+                match self.byte_order() {
+                    ByteOrder::LittleEndian => self.$read_le(ptr),
+                    ByteOrder::BigEndian => self.$read_be(ptr),
+                }
+            }
+            fn $read_le(&self, ptr: &mut Ptr) -> Option<$ty> {
+                self.read_array(ptr).map($ty::from_le_bytes)
+            }
+            fn $read_be(&self, ptr: &mut Ptr) -> Option<$ty> {
+                self.read_array(ptr).map($ty::from_be_bytes)
+            }
+        )*
+    };
+}
+
 /// Something that is readable with a pointer
 ///
 /// Pointer `Ptr` should contain the current reading position
 /// which should be mutated after each read.
+///
+/// Multi-byte values are read in the order returned by `byte_order`, which is little-endian by default.
 pub trait ByteReadable<Ptr> {
     fn read(&self, ptr: &mut Ptr) -> Option<u8>;
     fn has_next(&self, ptr: &Ptr) -> bool;
@@ -15,16 +47,156 @@ pub trait ByteReadable<Ptr> {
         Some(result)
     }
 
-    fn read_u16(&self, ptr: &mut Ptr) -> Option<u16> {
-        let bytes = self.read_n(ptr, 2)?;
-        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    /// Reads exactly `N` bytes.
+    fn read_array<const N: usize>(&self, ptr: &mut Ptr) -> Option<[u8; N]> {
+        let mut result = [0; N];
+        for byte in result.iter_mut() {
+            *byte = self.read(ptr)?;
+        }
+        Some(result)
+    }
+
+    fn byte_order(&self) -> ByteOrder {
+        ByteOrder::LittleEndian
+    }
+
+    fn read_i8(&self, ptr: &mut Ptr) -> Option<i8> {
+        self.read_array(ptr).map(i8::from_le_bytes)
+    }
+
+    fixed_width_readers! {
+        u16: read_u16, read_u16_le, read_u16_be;
+        i16: read_i16, read_i16_le, read_i16_be;
+        u32: read_u32, read_u32_le, read_u32_be;
+        i32: read_i32, read_i32_le, read_i32_be;
+        u64: read_u64, read_u64_le, read_u64_be;
+        i64: read_i64, read_i64_le, read_i64_be;
+        f32: read_f32, read_f32_le, read_f32_be;
+        f64: read_f64, read_f64_le, read_f64_be;
+    }
+
+    /// Reads an unsigned LEB128 varint.
+    ///
+    /// Returns `None` if the value does not fit into `u64`.
+    fn read_uleb128(&self, ptr: &mut Ptr) -> Option<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read(ptr)?;
+            let bits = u64::from(byte & 0x7f);
+            if shift >= 64 || (shift == 63 && bits > 1) {
+                return None;
+            }
+            result |= bits << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+    }
+
+    /// Reads a signed LEB128 varint.
+    ///
+    /// Returns `None` if the value does not fit into `i64`.
+    fn read_sleb128(&self, ptr: &mut Ptr) -> Option<i64> {
+        let mut result: i64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read(ptr)?;
+            let bits = byte & 0x7f;
+            if shift >= 64 || (shift == 63 && bits != 0x00 && bits != 0x7f) {
+                return None;
+            }
+            result |= i64::from(bits) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Some(result);
+            }
+        }
+    }
+
+    /// Reads bytes prefixed with their length encoded as an unsigned LEB128 varint.
+    fn read_bytes(&self, ptr: &mut Ptr) -> Option<Vec<u8>> {
+        let len = self.read_uleb128(ptr)?;
+        self.read_n(ptr, usize::try_from(len).ok()?)
     }
-    fn read_u32(&self, ptr: &mut Ptr) -> Option<u32> {
-        let bytes = self.read_n(ptr, 4)?;
-        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+
+    /// Reads a UTF-8 string prefixed with its length in bytes (see `read_bytes`).
+    ///
+    /// Returns `None` if the string is not valid UTF-8.
+    fn read_string(&self, ptr: &mut Ptr) -> Option<String> {
+        String::from_utf8(self.read_bytes(ptr)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_readable::{ByteOrder, ByteReadable};
+    use crate::parsing::{RawBytes, RawBytesPointer};
+
+    fn raw_bytes(data: Vec<u8>) -> (RawBytes<'static>, RawBytesPointer) {
+        (RawBytes::from_bytes(data), RawBytesPointer::new())
     }
-    fn read_i32(&self, ptr: &mut Ptr) -> Option<i32> {
-        let bytes = self.read_n(ptr, 4)?;
-        Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+
+    #[test]
+    fn fixed_width_values_should_be_little_endian() {
+        let mut data = vec![0xff];
+        data.extend(&(-2i16).to_le_bytes());
+        data.extend(&u64::MAX.to_le_bytes());
+        data.extend(&1.5f32.to_le_bytes());
+        data.extend(&(-0.25f64).to_le_bytes());
+        let (bytes, mut ptr) = raw_bytes(data);
+        assert_eq!(Some(-1), bytes.read_i8(&mut ptr));
+        assert_eq!(Some(-2), bytes.read_i16(&mut ptr));
+        assert_eq!(Some(u64::MAX), bytes.read_u64(&mut ptr));
+        assert_eq!(Some(1.5), bytes.read_f32(&mut ptr));
+        assert_eq!(Some(-0.25), bytes.read_f64(&mut ptr));
+        assert_eq!(None, bytes.read_i64(&mut ptr));
+    }
+
+    #[test]
+    fn values_should_be_read_in_byte_order() {
+        let data = vec![1, 2, 1, 2, 1, 2];
+        let bytes = RawBytes::from_bytes(data).with_byte_order(ByteOrder::BigEndian);
+        let mut ptr = RawBytesPointer::new();
+        assert_eq!(Some(0x0102), bytes.read_u16(&mut ptr));
+        assert_eq!(Some(0x0201), bytes.read_u16_le(&mut ptr));
+        assert_eq!(Some(0x0102), bytes.read_u16_be(&mut ptr));
+    }
+
+    #[test]
+    fn varints_should_be_read() {
+        let (bytes, mut ptr) = raw_bytes(vec![0xe5, 0x8e, 0x26, 0xc0, 0xbb, 0x78, 0x7f, 0x3f]);
+        assert_eq!(Some(624485), bytes.read_uleb128(&mut ptr));
+        assert_eq!(Some(-123456), bytes.read_sleb128(&mut ptr));
+        assert_eq!(Some(-1), bytes.read_sleb128(&mut ptr));
+        assert_eq!(Some(63), bytes.read_sleb128(&mut ptr));
+    }
+
+    #[test]
+    fn too_long_varints_should_not_be_read() {
+        let (bytes, mut ptr) = raw_bytes(vec![0xff; 11]);
+        assert_eq!(None, bytes.read_uleb128(&mut ptr));
+        let (bytes, mut ptr) = raw_bytes(vec![0x80, 0x80]);
+        assert_eq!(None, bytes.read_uleb128(&mut ptr));
+        let mut data = vec![0x80; 9];
+        data.push(0x01);
+        let (bytes, mut ptr) = raw_bytes(data);
+        assert_eq!(None, bytes.read_sleb128(&mut ptr));
+        let mut data = vec![0x80; 9];
+        data.push(0x7f);
+        let (bytes, mut ptr) = raw_bytes(data);
+        assert_eq!(Some(i64::MIN), bytes.read_sleb128(&mut ptr));
+    }
+
+    #[test]
+    fn length_prefixed_strings_should_be_read() {
+        let (bytes, mut ptr) = raw_bytes(vec![2, b'h', b'i', 2, 0xff, 0xfe, 1]);
+        assert_eq!(Some("hi".to_string()), bytes.read_string(&mut ptr));
+        assert_eq!(None, bytes.read_string(&mut ptr));
+        assert_eq!(None, bytes.read_bytes(&mut ptr));
     }
 }
@@ -1,24 +1,46 @@
 use crate::byte_readable::ByteReadable;
 use crate::exception::Exception;
 use crate::runtime::exceptions::ConstantNotFound;
-use crate::InstructionPointer;
+use crate::{ExceptionHandler, InstructionPointer};
+use std::borrow::Cow;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
 
 /// Executable code with a list of constants.
 ///
-/// Chunk contains a list of `constants` and executable `code`.
-pub struct Chunk<Constant> {
+/// Chunk contains a list of `constants`, executable `code`
+/// and the `exception_handlers` that cover regions of the code.
+/// The code is either owned or borrowed from the buffer it was parsed from.
+pub struct Chunk<'c, Constant> {
     pub constants: Vec<Constant>,
-    pub code: Vec<u8>,
+    pub code: Cow<'c, [u8]>,
+    pub exception_handlers: Vec<ExceptionHandler>,
 }
 
 /// A list of chunks
-pub struct Code<Constant> {
-    pub chunks: Vec<Chunk<Constant>>,
+pub struct Code<'c, Constant> {
+    pub chunks: Vec<Chunk<'c, Constant>>,
 }
 
-impl<Constant> Code<Constant> {
+impl<'c, Constant> Chunk<'c, Constant> {
+    /// Copies the code if it is borrowed.
+    pub fn into_owned(self) -> Chunk<'static, Constant> {
+        Chunk {
+            constants: self.constants,
+            code: Cow::Owned(self.code.into_owned()),
+            exception_handlers: self.exception_handlers,
+        }
+    }
+}
+
+impl<'c, Constant> Code<'c, Constant> {
+    /// Copies the code of the chunks that borrow it.
+    pub fn into_owned(self) -> Code<'static, Constant> {
+        Code {
+            chunks: self.chunks.into_iter().map(Chunk::into_owned).collect(),
+        }
+    }
+
     pub fn get_constant(
         &self,
         chunk_id: usize,
@@ -29,12 +51,12 @@ impl<Constant> Code<Constant> {
             .ok_or_else(|| Exception::from(ConstantNotFound(chunk_id, constant_id)))
     }
 
-    pub fn get_chunk(&self, chunk_id: usize) -> Option<&Chunk<Constant>> {
+    pub fn get_chunk(&self, chunk_id: usize) -> Option<&Chunk<'c, Constant>> {
         self.chunks.get(chunk_id)
     }
 }
 
-impl<Constant> ByteReadable<InstructionPointer> for Code<Constant> {
+impl<'c, Constant> ByteReadable<InstructionPointer> for Code<'c, Constant> {
     fn read(&self, ptr: &mut InstructionPointer) -> Option<u8> {
         let chunk = self.get_chunk(ptr.chunk_id)?;
         ptr.read_and_advance(chunk)
@@ -46,16 +68,17 @@ impl<Constant> ByteReadable<InstructionPointer> for Code<Constant> {
     }
 }
 
-impl<Constant: Debug> Debug for Chunk<Constant> {
+impl<'c, Constant: Debug> Debug for Chunk<'c, Constant> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Chunk")
             .field("constants", &self.constants)
             .field("code", &self.code)
+            .field("exception_handlers", &self.exception_handlers)
             .finish()
     }
 }
 
-impl<Constant: Debug> Debug for Code<Constant> {
+impl<'c, Constant: Debug> Debug for Code<'c, Constant> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_list().entries(&self.chunks).finish()
     }
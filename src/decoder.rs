@@ -0,0 +1,91 @@
+use std::fmt::Debug;
+
+use crate::instruction::Instruction;
+use crate::instruction_table::InstructionTable;
+
+/// An instruction that was read from the code of a chunk
+pub struct DecodedInstruction<'a, 'c, Constant, Value: Debug> {
+    /// The offset of the opcode in the chunk
+    pub offset: usize,
+    pub op_code: u8,
+    /// `None` if the opcode is not in the `InstructionTable`
+    pub instruction: Option<&'a Instruction<Constant, Value>>,
+    /// The bytes of the arguments, fewer than `byte_arity` if the code ended early
+    pub arguments: &'c [u8],
+    /// `true` if the code ended before all arguments were read
+    pub truncated: bool,
+}
+
+impl<'a, 'c, Constant, Value: Debug> DecodedInstruction<'a, 'c, Constant, Value> {
+    /// The number of bytes that the instruction occupies in the chunk
+    pub fn size(&self) -> usize {
+        1 + self.arguments.len()
+    }
+
+    /// The offset of the next instruction
+    pub fn end(&self) -> usize {
+        self.offset + self.size()
+    }
+
+    /// The name of the instruction or `UNKNOWN(op_code)`
+    pub fn name(&self) -> String {
+        match self.instruction {
+            Some(instruction) => instruction.name.to_string(),
+            None => format!("UNKNOWN({})", self.op_code),
+        }
+    }
+}
+
+/// Splits the code of a chunk into instructions.
+///
+/// Instructions are read sequentially starting from the first byte.
+/// Unknown opcodes are treated as instructions without arguments.
+pub fn decode<'a, 'c, Constant, Value: Debug>(
+    code: &'c [u8],
+    instruction_table: &InstructionTable<'a, Constant, Value>,
+) -> Vec<DecodedInstruction<'a, 'c, Constant, Value>> {
+    let mut result = vec![];
+    let mut offset = 0;
+    while offset < code.len() {
+        let op_code = code[offset];
+        let instruction = instruction_table.get_instruction(op_code);
+        let byte_arity = instruction
+            .map(|instruction| instruction.instruction_fn.byte_arity())
+            .unwrap_or(0);
+        let arguments_start = offset + 1;
+        let arguments_end = usize::min(arguments_start + byte_arity, code.len());
+        result.push(DecodedInstruction {
+            offset,
+            op_code,
+            instruction,
+            arguments: &code[arguments_start..arguments_end],
+            truncated: arguments_end - arguments_start < byte_arity,
+        });
+        offset = arguments_end;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decoder::decode;
+    use crate::instruction::Instruction;
+    use crate::instruction_table::InstructionTable;
+    use crate::test_support::{noop, raw, Constant, Value};
+
+    const PUSH: Instruction<Constant, Value> = raw(0, "PUSH", 2, noop);
+
+    #[test]
+    fn decode_should_split_code_into_instructions() {
+        let table = InstructionTable::instructions(&[&PUSH]);
+        let instructions = decode(&[0, 1, 2, 7, 0, 3], &table);
+        assert_eq!(3, instructions.len());
+        assert_eq!("PUSH", instructions[0].name());
+        assert_eq!(&[1, 2], instructions[0].arguments);
+        assert_eq!("UNKNOWN(7)", instructions[1].name());
+        assert_eq!(3, instructions[1].offset);
+        assert_eq!(4, instructions[2].offset);
+        assert!(instructions[2].truncated);
+        assert_eq!(&[3], instructions[2].arguments);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::byte_readable::ByteReadable;
 use crate::exception::Exception;
+use crate::runtime::exceptions::UnexpectedEndOfCode;
 use crate::{InstructionPointer, Machine};
 use std::fmt::Debug;
 
@@ -23,6 +25,9 @@ pub enum InstructionFn<Constant, Value: Debug> {
     Const(fn() -> Value),
     UnaryOp(fn(value: Value) -> Result<Value, Exception>),
     BinaryOp(fn(left: Value, right: Value) -> Result<Value, Exception>),
+    /// Calls a host function registered in the `Machine`.
+    /// Accepts 1 byte -- the id of the host function.
+    CallNative,
 }
 
 pub type RawInstructionFn<Constant, Value> = fn(
@@ -32,10 +37,10 @@ pub type RawInstructionFn<Constant, Value> = fn(
 
 impl<Constant, Value: Debug> InstructionFn<Constant, Value> {
     pub fn byte_arity(&self) -> usize {
-        if let InstructionFn::Raw { byte_arity, .. } = self {
-            *byte_arity
-        } else {
-            0
+        match self {
+            InstructionFn::Raw { byte_arity, .. } => *byte_arity,
+            InstructionFn::CallNative => 1,
+            _ => 0,
         }
     }
     pub fn run(
@@ -60,6 +65,13 @@ impl<Constant, Value: Debug> InstructionFn<Constant, Value> {
                 let result = (*operator)(left, right)?;
                 machine.push_operand(result);
             }
+            InstructionFn::CallNative => {
+                let mut args_ip = args_ip;
+                let native_id = machine.read(&mut args_ip).ok_or(UnexpectedEndOfCode {
+                    chunk_id: args_ip.chunk_id,
+                })?;
+                machine.call_native(usize::from(native_id))?;
+            }
         };
         Ok(())
     }
@@ -3,15 +3,25 @@ use std::collections::HashMap;
 use crate::instruction::Instruction;
 use std::fmt::Debug;
 
+/// The cost of instructions that were not assigned a cost explicitly
+pub const DEFAULT_INSTRUCTION_COST: u64 = 1;
+
 /// A set of instruction definitions
+///
+/// Each instruction has a cost that is subtracted from the fuel of the `Machine`
+/// when the instruction is run.
 pub struct InstructionTable<'a, Constant, Value: Debug> {
     instructions: HashMap<u8, &'a Instruction<Constant, Value>>,
+    names: HashMap<&'static str, u8>,
+    costs: HashMap<u8, u64>,
 }
 
 impl<'a, Constant, Value: Debug> InstructionTable<'a, Constant, Value> {
     pub(crate) fn new() -> InstructionTable<'a, Constant, Value> {
         InstructionTable {
             instructions: HashMap::new(),
+            names: HashMap::new(),
+            costs: HashMap::new(),
         }
     }
 
@@ -33,6 +43,9 @@ impl<'a, Constant, Value: Debug> InstructionTable<'a, Constant, Value> {
                 prev_instruction.name, instruction.name
             );
         }
+        self.names
+            .entry(instruction.name)
+            .or_insert(instruction.op_code);
     }
 
     pub fn get_instruction(&self, op_code: u8) -> Option<&'a Instruction<Constant, Value>> {
@@ -40,6 +53,28 @@ impl<'a, Constant, Value: Debug> InstructionTable<'a, Constant, Value> {
             .get(&op_code)
             .map(|instruction| &**instruction)
     }
+
+    /// Finds the instruction by its name.
+    /// If several instructions share the name, the first registered one is returned.
+    pub fn get_instruction_by_name(&self, name: &str) -> Option<&'a Instruction<Constant, Value>> {
+        self.names
+            .get(name)
+            .and_then(|op_code| self.get_instruction(*op_code))
+    }
+
+    pub fn set_cost(&mut self, op_code: u8, cost: u64) {
+        if !self.instructions.contains_key(&op_code) {
+            panic!("No instruction with opcode {} is registered", op_code);
+        }
+        self.costs.insert(op_code, cost);
+    }
+
+    pub fn get_cost(&self, op_code: u8) -> u64 {
+        self.costs
+            .get(&op_code)
+            .cloned()
+            .unwrap_or(DEFAULT_INSTRUCTION_COST)
+    }
 }
 
 #[cfg(test)]
@@ -85,6 +120,33 @@ mod tests {
         assert!(ptr::eq(&ADD, table.get_instruction(0).unwrap()))
     }
 
+    #[test]
+    fn registered_instruction_should_be_gettable_by_name() {
+        let table = InstructionTable::instructions(&[&ADD]);
+        assert!(ptr::eq(&ADD, table.get_instruction_by_name("ADD").unwrap()));
+        assert!(table.get_instruction_by_name("MUL").is_none());
+    }
+
+    #[test]
+    fn instruction_cost_should_be_1_by_default() {
+        let table = InstructionTable::instructions(&[&ADD]);
+        assert_eq!(1, table.get_cost(0));
+    }
+
+    #[test]
+    fn instruction_cost_should_be_settable() {
+        let mut table = InstructionTable::instructions(&[&ADD]);
+        table.set_cost(0, 10);
+        assert_eq!(10, table.get_cost(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn setting_cost_of_unknown_instruction_panics() {
+        let mut table = InstructionTable::instructions(&[&ADD]);
+        table.set_cost(1, 10);
+    }
+
     #[test]
     #[should_panic]
     fn registering_instructions_with_duplicate_opcodes_panics() {
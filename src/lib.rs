@@ -1,17 +1,43 @@
-pub use byte_readable::ByteReadable;
+pub use assembly::exceptions as assembly_exceptions;
+pub use assembly::{disassemble, disassemble_chunk, Assembler};
+pub use byte_readable::{ByteOrder, ByteReadable};
 pub use code::{Chunk, Code};
+pub use decoder::{decode, DecodedInstruction};
 pub use exception::{Exception, ExceptionType};
 pub use instruction::{Instruction, InstructionFn, RawInstructionFn};
-pub use instruction_table::InstructionTable;
+pub use instruction_table::{InstructionTable, DEFAULT_INSTRUCTION_COST};
+pub use observers::{
+    ChunkCoverage, CoverageCollector, CoverageReport, ProfileEntry, Profiler, TraceFormat, Tracer,
+    UncoveredRange,
+};
 pub use parsing::exceptions as parsing_exceptions;
-pub use parsing::{CodeParser, ConstantParser, ConstantParserTable, RawBytes, RawBytesPointer};
+pub use parsing::std_constants;
+pub use parsing::{
+    ChunkLayout, CodeHeader, CodeParser, ConstantParser, ConstantParserFn, ConstantParserTable,
+    ParseConstant, RawBytes, RawBytesPointer, MAGIC_NUMBER,
+};
 pub use runtime::exceptions as runtime_exceptions;
-pub use runtime::{CallFrame, InstructionPointer, Machine};
+pub use runtime::{
+    CallFrame, Debugger, ExceptionHandler, ExecutionObserver, InstructionPointer, Machine,
+    NativeFn, NativeFunction, NativeRegistry, Outcome, Pause, StackTraceEntry, StepStatus, VmError,
+    MAX_NATIVE_FUNCTIONS,
+};
+pub use verification::exceptions as verification_exceptions;
+pub use verification::{ArgumentKind, Verifier};
+pub use writing::exceptions as writing_exceptions;
+pub use writing::{CodeWriter, ConstantSerializer, ConstantSerializerFn, ConstantSerializerTable};
 
+mod assembly;
 mod byte_readable;
 mod code;
+mod decoder;
 mod exception;
 mod instruction;
 mod instruction_table;
+mod observers;
 mod parsing;
 mod runtime;
+#[cfg(test)]
+pub(crate) mod test_support;
+mod verification;
+mod writing;
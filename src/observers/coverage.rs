@@ -0,0 +1,203 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::code::Code;
+use crate::decoder::decode;
+use crate::instruction::Instruction;
+use crate::instruction_table::InstructionTable;
+use crate::runtime::{ExecutionObserver, InstructionPointer, Machine};
+
+/// Records the offsets of instructions that were run in each chunk.
+pub struct CoverageCollector {
+    executed: HashMap<usize, BTreeSet<usize>>,
+}
+
+impl CoverageCollector {
+    pub fn new() -> CoverageCollector {
+        CoverageCollector {
+            executed: HashMap::new(),
+        }
+    }
+
+    pub fn is_executed(&self, chunk_id: usize, offset: usize) -> bool {
+        self.executed
+            .get(&chunk_id)
+            .map(|offsets| offsets.contains(&offset))
+            .unwrap_or(false)
+    }
+
+    /// Computes the coverage of each chunk of `code`.
+    ///
+    /// Chunks are split into instructions using `instruction_table`.
+    pub fn report<Constant, Value: Debug>(
+        &self,
+        code: &Code<Constant>,
+        instruction_table: &InstructionTable<Constant, Value>,
+    ) -> CoverageReport {
+        let chunks = code
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(chunk_id, chunk)| {
+                let mut coverage = ChunkCoverage {
+                    chunk_id,
+                    total_bytes: chunk.code.len(),
+                    covered_bytes: 0,
+                    uncovered: vec![],
+                };
+                for instruction in decode(&chunk.code, instruction_table) {
+                    if self.is_executed(chunk_id, instruction.offset) {
+                        coverage.covered_bytes += instruction.size();
+                        continue;
+                    }
+                    match coverage.uncovered.last_mut() {
+                        Some(range) if range.end == instruction.offset => {
+                            range.end = instruction.end();
+                            range.instructions.push(instruction.name());
+                        }
+                        _ => coverage.uncovered.push(UncoveredRange {
+                            start: instruction.offset,
+                            end: instruction.end(),
+                            instructions: vec![instruction.name()],
+                        }),
+                    }
+                }
+                coverage
+            })
+            .collect();
+        CoverageReport { chunks }
+    }
+}
+
+impl Default for CoverageCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Constant, Value: Debug> ExecutionObserver<Constant, Value> for CoverageCollector {
+    fn before_instruction(
+        &mut self,
+        _machine: &Machine<Constant, Value>,
+        ip: &InstructionPointer,
+        _instruction: &Instruction<Constant, Value>,
+    ) {
+        self.executed
+            .entry(ip.chunk_id)
+            .or_default()
+            .insert(ip.instruction_pointer);
+    }
+}
+
+/// A sequence of instructions that were never run
+///
+/// Contains bytes from `start` (inclusive) to `end` (exclusive) and the names of the instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncoveredRange {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: Vec<String>,
+}
+
+/// Coverage of a single chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkCoverage {
+    pub chunk_id: usize,
+    pub total_bytes: usize,
+    pub covered_bytes: usize,
+    pub uncovered: Vec<UncoveredRange>,
+}
+
+impl ChunkCoverage {
+    /// The percentage of the code bytes that belong to executed instructions
+    pub fn percentage(&self) -> f64 {
+        if self.total_bytes == 0 {
+            100.0
+        } else {
+            self.covered_bytes as f64 * 100.0 / self.total_bytes as f64
+        }
+    }
+}
+
+/// Coverage of all chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageReport {
+    pub chunks: Vec<ChunkCoverage>,
+}
+
+impl Display for CoverageReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for chunk in &self.chunks {
+            writeln!(
+                f,
+                "Chunk #{}: {:.2}% ({}/{} bytes)",
+                chunk.chunk_id,
+                chunk.percentage(),
+                chunk.covered_bytes,
+                chunk.total_bytes
+            )?;
+            for range in &chunk.uncovered {
+                writeln!(
+                    f,
+                    "\tnot executed {}..{}: {}",
+                    range.start,
+                    range.end,
+                    range.instructions.join(", ")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instruction_table::InstructionTable;
+    use crate::observers::coverage::{CoverageCollector, CoverageReport, UncoveredRange};
+    use crate::test_support::{code, run_observed, INSTRUCTIONS};
+
+    fn report() -> CoverageReport {
+        let chunks = vec![
+            // JUMP 4; PUSH 1; PUSH 2; PUSH 3
+            vec![6, 4, 0, 1, 0, 2, 0, 3],
+            vec![0, 5],
+            vec![],
+        ];
+        let collector = run_observed(chunks.clone(), CoverageCollector::new());
+        collector.report(
+            &code(chunks),
+            &InstructionTable::instructions(&INSTRUCTIONS),
+        )
+    }
+
+    #[test]
+    fn report_should_contain_executed_bytes() {
+        let report = report();
+        assert_eq!(4, report.chunks[0].covered_bytes);
+        assert_eq!(8, report.chunks[0].total_bytes);
+        assert_eq!(50.0, report.chunks[0].percentage());
+        assert_eq!(0.0, report.chunks[1].percentage());
+        assert_eq!(100.0, report.chunks[2].percentage());
+    }
+
+    #[test]
+    fn report_should_list_uncovered_ranges() {
+        let report = report();
+        assert_eq!(
+            vec![UncoveredRange {
+                start: 2,
+                end: 6,
+                instructions: vec!["PUSH".to_string(), "PUSH".to_string()],
+            }],
+            report.chunks[0].uncovered
+        );
+    }
+
+    #[test]
+    fn report_should_be_displayed_per_chunk() {
+        let report = report().to_string();
+        assert!(report.contains("Chunk #0: 50.00% (4/8 bytes)"));
+        assert!(report.contains("not executed 2..6: PUSH, PUSH"));
+    }
+}
@@ -0,0 +1,7 @@
+pub use coverage::{ChunkCoverage, CoverageCollector, CoverageReport, UncoveredRange};
+pub use profiler::{ProfileEntry, Profiler};
+pub use tracer::{TraceFormat, Tracer};
+
+mod coverage;
+mod profiler;
+mod tracer;
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Write as FmtWrite};
+use std::io;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::instruction::Instruction;
+use crate::runtime::{CallFrame, ExecutionObserver, InstructionPointer, Machine};
+
+/// Execution statistics of one opcode or one call frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub count: u64,
+    pub total_time: Duration,
+}
+
+impl ProfileEntry {
+    fn new(name: String) -> ProfileEntry {
+        ProfileEntry {
+            name,
+            count: 0,
+            total_time: Duration::default(),
+        }
+    }
+
+    fn record(&mut self, time: Duration) {
+        self.count += 1;
+        self.total_time += time;
+    }
+}
+
+/// The instruction that is being run and the call stack it was run in
+struct InstructionStart {
+    frame: String,
+    stack: String,
+    time: Instant,
+}
+
+/// Records how many times each instruction was run and how much time it took.
+///
+/// Statistics are grouped by opcode name (`Instruction::name`) and by call frame,
+/// frames are identified by their name and chunk id.
+/// The time of each instruction is also attributed to the full call stack,
+/// which can be written in the folded stack format consumed by flamegraph tools.
+pub struct Profiler {
+    opcodes: HashMap<&'static str, ProfileEntry>,
+    frames: HashMap<String, ProfileEntry>,
+    stacks: HashMap<String, Duration>,
+    current_stack: Option<String>,
+    instruction_start: Option<InstructionStart>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            opcodes: HashMap::new(),
+            frames: HashMap::new(),
+            stacks: HashMap::new(),
+            current_stack: None,
+            instruction_start: None,
+        }
+    }
+
+    /// Returns statistics for each opcode sorted by total time in descending order.
+    pub fn opcode_entries(&self) -> Vec<ProfileEntry> {
+        sorted_entries(self.opcodes.values())
+    }
+
+    /// Returns statistics for each call frame sorted by total time in descending order.
+    pub fn frame_entries(&self) -> Vec<ProfileEntry> {
+        sorted_entries(self.frames.values())
+    }
+
+    /// Renders opcode and call frame statistics as text tables.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        write_table(&mut report, "Opcode", &self.opcode_entries());
+        report.push('\n');
+        write_table(&mut report, "Frame", &self.frame_entries());
+        report
+    }
+
+    /// Writes the time spent in each call stack in the folded stack format.
+    ///
+    /// Each line contains frames separated by `;` starting from the outermost one
+    /// and the total time in nanoseconds.
+    pub fn write_folded(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        stacks.sort();
+        for (stack, time) in stacks {
+            writeln!(writer, "{} {}", stack, time.as_nanos())?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Constant, Value: Debug> ExecutionObserver<Constant, Value> for Profiler {
+    fn before_instruction(
+        &mut self,
+        machine: &Machine<Constant, Value>,
+        _ip: &InstructionPointer,
+        _instruction: &Instruction<Constant, Value>,
+    ) {
+        let frames = machine.frames();
+        if self.current_stack.is_none() {
+            let names: Vec<String> = frames.iter().map(frame_name).collect();
+            self.current_stack = Some(names.join(";"));
+        }
+        self.instruction_start = Some(InstructionStart {
+            frame: frames.last().map(frame_name).unwrap_or_default(),
+            stack: self.current_stack.clone().unwrap_or_default(),
+            time: Instant::now(),
+        });
+    }
+
+    fn after_instruction(
+        &mut self,
+        _machine: &Machine<Constant, Value>,
+        _ip: &InstructionPointer,
+        instruction: &Instruction<Constant, Value>,
+    ) {
+        let start = match self.instruction_start.take() {
+            Some(start) => start,
+            None => return,
+        };
+        let time = start.time.elapsed();
+        self.opcodes
+            .entry(instruction.name)
+            .or_insert_with(|| ProfileEntry::new(instruction.name.to_string()))
+            .record(time);
+        let frame = start.frame;
+        self.frames
+            .entry(frame.clone())
+            .or_insert_with(|| ProfileEntry::new(frame))
+            .record(time);
+        *self.stacks.entry(start.stack).or_default() += time;
+    }
+
+    fn frame_pushed(&mut self, _machine: &Machine<Constant, Value>, _frame: &CallFrame) {
+        self.current_stack = None;
+    }
+
+    fn frame_discarded(&mut self, _machine: &Machine<Constant, Value>, _frame: &CallFrame) {
+        self.current_stack = None;
+    }
+}
+
+fn frame_name(frame: &CallFrame) -> String {
+    format!("{} #{}", frame.name, frame.instruction_pointer.chunk_id)
+}
+
+fn sorted_entries<'e>(entries: impl Iterator<Item = &'e ProfileEntry>) -> Vec<ProfileEntry> {
+    let mut entries: Vec<ProfileEntry> = entries.cloned().collect();
+    entries.sort_by(|a, b| {
+        b.total_time
+            .cmp(&a.total_time)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    entries
+}
+
+fn write_table(output: &mut String, title: &str, entries: &[ProfileEntry]) {
+    let width = entries
+        .iter()
+        .map(|entry| entry.name.len())
+        .chain(Some(title.len()))
+        .max()
+        .unwrap_or_default();
+    let _ = writeln!(
+        output,
+        "{:<width$}  {:>10}  {:>14}  {:>12}",
+        title,
+        "Count",
+        "Total (us)",
+        "Avg (ns)",
+        width = width
+    );
+    for entry in entries {
+        let average = entry.total_time.as_nanos() / u128::from(entry.count.max(1));
+        let _ = writeln!(
+            output,
+            "{:<width$}  {:>10}  {:>14.3}  {:>12}",
+            entry.name,
+            entry.count,
+            entry.total_time.as_secs_f64() * 1_000_000.0,
+            average,
+            width = width
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observers::profiler::Profiler;
+    use crate::test_support::run_observed;
+
+    fn profile() -> Profiler {
+        // CALL #1; CALL #1 / RET
+        run_observed(vec![vec![2, 1, 2, 1], vec![4]], Profiler::new())
+    }
+
+    #[test]
+    fn profiler_should_count_opcodes() {
+        let profiler = profile();
+        let mut counts: Vec<(String, u64)> = profiler
+            .opcode_entries()
+            .into_iter()
+            .map(|entry| (entry.name, entry.count))
+            .collect();
+        counts.sort();
+        assert_eq!(
+            vec![("CALL".to_string(), 2), ("RET".to_string(), 2)],
+            counts
+        );
+    }
+
+    #[test]
+    fn profiler_should_count_frames() {
+        let profiler = profile();
+        let mut counts: Vec<(String, u64)> = profiler
+            .frame_entries()
+            .into_iter()
+            .map(|entry| (entry.name, entry.count))
+            .collect();
+        counts.sort();
+        assert_eq!(
+            vec![("f #1".to_string(), 2), ("main #0".to_string(), 2)],
+            counts
+        );
+    }
+
+    #[test]
+    fn profiler_should_write_folded_stacks() {
+        let profiler = profile();
+        let mut output: Vec<u8> = vec![];
+        profiler.write_folded(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let stacks: Vec<&str> = output
+            .lines()
+            .map(|line| line.rsplitn(2, ' ').last().unwrap())
+            .collect();
+        assert_eq!(vec!["main #0", "main #0;f #1"], stacks);
+    }
+
+    #[test]
+    fn report_should_contain_all_entries() {
+        let report = profile().report();
+        for name in &["CALL", "RET", "main #0", "f #1"] {
+            assert!(report.contains(name));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt::Debug;
+use std::io;
+use std::io::Write;
+use std::time::Instant;
+
+use crate::instruction::Instruction;
+use crate::runtime::{CallFrame, ExecutionObserver, InstructionPointer, Machine};
+
+/// The format of the records written by `Tracer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One JSON object per executed instruction on a separate line.
+    ///
+    /// If `include_stack` is `true`, each record also contains the operand stack
+    /// with values formatted with `Debug`.
+    JsonLines { include_stack: bool },
+    /// A JSON array of trace events with a begin and an end event for each call frame,
+    /// which can be loaded into Chrome trace viewers.
+    ChromeTrace,
+}
+
+/// Writes a structured trace of the execution.
+///
+/// Write errors do not stop the execution, the first one is returned from `finish`.
+pub struct Tracer<W: Write> {
+    writer: W,
+    format: TraceFormat,
+    start_time: Instant,
+    open_frames: Vec<String>,
+    n_events: usize,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(writer: W, format: TraceFormat) -> Tracer<W> {
+        Tracer {
+            writer,
+            format,
+            start_time: Instant::now(),
+            open_frames: vec![],
+            n_events: 0,
+            error: None,
+        }
+    }
+
+    pub fn json_lines(writer: W, include_stack: bool) -> Tracer<W> {
+        Tracer::new(writer, TraceFormat::JsonLines { include_stack })
+    }
+
+    pub fn chrome_trace(writer: W) -> Tracer<W> {
+        Tracer::new(writer, TraceFormat::ChromeTrace)
+    }
+
+    /// Completes the trace and flushes the writer.
+    ///
+    /// In the Chrome trace format ends all frames that are still active and closes the array.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let TraceFormat::ChromeTrace = self.format {
+            while let Some(name) = self.open_frames.pop() {
+                self.write_frame_event(&name, "E");
+            }
+            let end = if self.n_events == 0 { "[]\n" } else { "\n]\n" };
+            self.write(end);
+        }
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.writer.flush()
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, data: &str) {
+        if self.error.is_none() {
+            if let Err(error) = self.writer.write_all(data.as_bytes()) {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn write_frame_event(&mut self, name: &str, phase: &str) {
+        let separator = if self.n_events == 0 { "[\n" } else { ",\n" };
+        let timestamp = self.start_time.elapsed().as_secs_f64() * 1_000_000.0;
+        let event = format!(
+            "{}{{\"name\":{},\"cat\":\"frame\",\"ph\":\"{}\",\"ts\":{:.3},\"pid\":1,\"tid\":1}}",
+            separator,
+            json_string(name),
+            phase,
+            timestamp
+        );
+        self.n_events += 1;
+        self.write(&event);
+    }
+}
+
+impl<Constant, Value: Debug, W: Write> ExecutionObserver<Constant, Value> for Tracer<W> {
+    fn before_instruction(
+        &mut self,
+        machine: &Machine<Constant, Value>,
+        ip: &InstructionPointer,
+        instruction: &Instruction<Constant, Value>,
+    ) {
+        let include_stack = match self.format {
+            TraceFormat::JsonLines { include_stack } => include_stack,
+            TraceFormat::ChromeTrace => return,
+        };
+        let mut record = format!(
+            "{{\"chunk\":{},\"ip\":{},\"opcode\":{},\"name\":{},\"stack_depth\":{}",
+            ip.chunk_id,
+            ip.instruction_pointer,
+            instruction.op_code,
+            json_string(instruction.name),
+            machine.operand_stack_len()
+        );
+        if include_stack {
+            let stack: Vec<String> = machine
+                .operands()
+                .iter()
+                .map(|value| json_string(&format!("{:?}", value)))
+                .collect();
+            record.push_str(&format!(",\"stack\":[{}]", stack.join(",")));
+        }
+        record.push_str("}\n");
+        self.write(&record);
+    }
+
+    fn frame_pushed(&mut self, _machine: &Machine<Constant, Value>, frame: &CallFrame) {
+        if let TraceFormat::ChromeTrace = self.format {
+            let name = format!("{} #{}", frame.name, frame.instruction_pointer.chunk_id);
+            self.write_frame_event(&name, "B");
+            self.open_frames.push(name);
+        }
+    }
+
+    fn frame_discarded(&mut self, _machine: &Machine<Constant, Value>, _frame: &CallFrame) {
+        if let TraceFormat::ChromeTrace = self.format {
+            if let Some(name) = self.open_frames.pop() {
+                self.write_frame_event(&name, "E");
+            }
+        }
+    }
+}
+
+/// Encodes the string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observers::tracer::{json_string, TraceFormat, Tracer};
+    use crate::test_support::run_observed;
+
+    fn trace(format: TraceFormat) -> String {
+        // PUSH 7; CALL #1 / RET
+        let chunks = vec![vec![0, 7, 2, 1], vec![4]];
+        let mut tracer = run_observed(chunks, Tracer::new(vec![], format));
+        tracer.finish().unwrap();
+        String::from_utf8(tracer.into_writer()).unwrap()
+    }
+
+    #[test]
+    fn json_lines_should_contain_record_per_instruction() {
+        let trace = trace(TraceFormat::JsonLines {
+            include_stack: false,
+        });
+        assert_eq!(
+            vec![
+                "{\"chunk\":0,\"ip\":0,\"opcode\":0,\"name\":\"PUSH\",\"stack_depth\":0}",
+                "{\"chunk\":0,\"ip\":2,\"opcode\":2,\"name\":\"CALL\",\"stack_depth\":1}",
+                "{\"chunk\":1,\"ip\":0,\"opcode\":4,\"name\":\"RET\",\"stack_depth\":1}",
+            ],
+            trace.lines().collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn json_lines_should_contain_stack_if_requested() {
+        let trace = trace(TraceFormat::JsonLines {
+            include_stack: true,
+        });
+        let last = trace.lines().last().unwrap();
+        assert!(last.ends_with(",\"stack_depth\":1,\"stack\":[\"7\"]}"));
+    }
+
+    #[test]
+    fn chrome_trace_should_contain_begin_and_end_events() {
+        let trace = trace(TraceFormat::ChromeTrace);
+        assert!(trace.starts_with("[\n"));
+        assert!(trace.ends_with("\n]\n"));
+        assert_eq!(2, trace.matches("\"ph\":\"B\"").count());
+        assert_eq!(2, trace.matches("\"ph\":\"E\"").count());
+        assert!(trace.contains("\"name\":\"main #0\""));
+        assert!(trace.contains("\"name\":\"f #1\""));
+    }
+
+    #[test]
+    fn json_string_should_escape_special_characters() {
+        assert_eq!("\"a\\\"b\\\\c\\n\\u0001\"", json_string("a\"b\\c\n\u{1}"));
+    }
+}
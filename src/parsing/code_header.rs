@@ -0,0 +1,159 @@
+use crate::byte_readable::ByteReadable;
+use crate::exception::Exception;
+use crate::parsing::exceptions::{CodeEndedAt, InvalidMagicNumber, UnsupportedFormatVersion};
+use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+
+/// The first bytes of every file with a header
+pub const MAGIC_NUMBER: [u8; 4] = *b"EXVM";
+
+/// Defines how the sizes of chunks are encoded.
+///
+/// `Compact` stores the number of constants as `u8` and the number of code bytes as `u16`.
+/// `Wide` stores both of them as `u32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkLayout {
+    #[default]
+    Compact,
+    Wide,
+}
+
+impl ChunkLayout {
+    /// The version of the bytecode format that is written in the header
+    pub fn format_version(self) -> u16 {
+        match self {
+            ChunkLayout::Compact => 1,
+            ChunkLayout::Wide => 2,
+        }
+    }
+
+    pub fn from_format_version(format_version: u16) -> Option<ChunkLayout> {
+        match format_version {
+            1 => Some(ChunkLayout::Compact),
+            2 => Some(ChunkLayout::Wide),
+            _ => None,
+        }
+    }
+
+    pub fn max_constants(self) -> usize {
+        match self {
+            ChunkLayout::Compact => usize::from(u8::MAX),
+            ChunkLayout::Wide => u32::MAX as usize,
+        }
+    }
+
+    pub fn max_code_bytes(self) -> usize {
+        match self {
+            ChunkLayout::Compact => usize::from(u16::MAX),
+            ChunkLayout::Wide => u32::MAX as usize,
+        }
+    }
+}
+
+/// An optional header that precedes the chunks.
+///
+/// Identifies the user-defined instruction set that the code was compiled for.
+/// The header is always little-endian, independent of the byte order of the chunks.
+/// In a binary file the header also starts with `MAGIC_NUMBER` and the format version,
+/// which defines the `ChunkLayout` of the chunks that follow the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeHeader {
+    pub instruction_set_id: u32,
+    pub instruction_set_version: u16,
+}
+
+impl CodeHeader {
+    pub fn new(instruction_set_id: u32, instruction_set_version: u16) -> CodeHeader {
+        CodeHeader {
+            instruction_set_id,
+            instruction_set_version,
+        }
+    }
+
+    /// Reads the header and checks the magic number and the format version.
+    ///
+    /// Returns the header and the layout of the chunks.
+    pub fn parse(
+        bytes: &RawBytes,
+        ptr: &mut RawBytesPointer,
+    ) -> Result<(CodeHeader, ChunkLayout), Exception> {
+        let offset = ptr.next_byte;
+        let magic_number = bytes
+            .read_n(ptr, MAGIC_NUMBER.len())
+            .ok_or_else(|| CodeEndedAt::new("magic_number", offset))?;
+        if magic_number != MAGIC_NUMBER {
+            return Err(Exception::from(InvalidMagicNumber(magic_number)));
+        }
+        let offset = ptr.next_byte;
+        let format_version = bytes
+            .read_u16_le(ptr)
+            .ok_or_else(|| CodeEndedAt::new("format_version", offset))?;
+        let layout = ChunkLayout::from_format_version(format_version)
+            .ok_or(UnsupportedFormatVersion(format_version))?;
+        let offset = ptr.next_byte;
+        let instruction_set_id = bytes
+            .read_u32_le(ptr)
+            .ok_or_else(|| CodeEndedAt::new("instruction_set_id", offset))?;
+        let offset = ptr.next_byte;
+        let instruction_set_version = bytes
+            .read_u16_le(ptr)
+            .ok_or_else(|| CodeEndedAt::new("instruction_set_version", offset))?;
+        let header = CodeHeader {
+            instruction_set_id,
+            instruction_set_version,
+        };
+        Ok((header, layout))
+    }
+
+    pub fn write(&self, layout: ChunkLayout, bytes: &mut Vec<u8>) {
+        bytes.extend(&MAGIC_NUMBER);
+        bytes.extend(&layout.format_version().to_le_bytes());
+        bytes.extend(&self.instruction_set_id.to_le_bytes());
+        bytes.extend(&self.instruction_set_version.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::code_header::{ChunkLayout, CodeHeader};
+    use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+
+    fn parse(bytes: Vec<u8>) -> Result<(CodeHeader, ChunkLayout), String> {
+        CodeHeader::parse(&RawBytes::from_bytes(bytes), &mut RawBytesPointer::new())
+            .map_err(|exception| exception.name)
+    }
+
+    #[test]
+    fn written_header_should_be_parsed_back() {
+        let header = CodeHeader::new(0xABCD, 3);
+        let mut bytes = vec![];
+        header.write(ChunkLayout::Compact, &mut bytes);
+        assert_eq!(12, bytes.len());
+        assert_eq!(Ok((header, ChunkLayout::Compact)), parse(bytes));
+    }
+
+    #[test]
+    fn header_should_define_chunk_layout() {
+        let header = CodeHeader::new(1, 1);
+        let mut bytes = vec![];
+        header.write(ChunkLayout::Wide, &mut bytes);
+        assert_eq!(Ok((header, ChunkLayout::Wide)), parse(bytes));
+    }
+
+    #[test]
+    fn header_with_wrong_magic_number_should_be_rejected() {
+        let bytes = vec![b'E', b'X', b'V', b'X', 1, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(Err("InvalidMagicNumber".to_string()), parse(bytes));
+    }
+
+    #[test]
+    fn header_with_unsupported_format_version_should_be_rejected() {
+        let bytes = vec![b'E', b'X', b'V', b'M', 9, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(Err("UnsupportedFormatVersion".to_string()), parse(bytes));
+    }
+
+    #[test]
+    fn truncated_header_should_be_rejected() {
+        let bytes = vec![b'E', b'X', b'V', b'M', 1, 0, 0];
+        assert_eq!(Err("CodeEndedAt".to_string()), parse(bytes));
+    }
+}
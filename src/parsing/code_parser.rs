@@ -1,30 +1,86 @@
 use crate::byte_readable::ByteReadable;
 use crate::code::{Chunk, Code};
 use crate::exception::Exception;
+use crate::parsing::code_header::{ChunkLayout, CodeHeader};
 use crate::parsing::constant_parser::ConstantParserTable;
 use crate::parsing::exceptions::{
-    ChunkParsingError, CodeEndedAt, EmptyCode, IllegalConstant, UnknownConstantType,
+    ChunkParsingError, CodeEndedAt, EmptyCode, IllegalConstant, InstructionSetMismatch, ReadFailed,
+    UnknownConstantType,
 };
 use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+use std::io::Read;
 
 /// Parses code from raw bytes using a table of constant parsers.
+///
+/// If a header is expected, the code must start with a `CodeHeader` that is equal to it
+/// and the layout of chunks is defined by the header.
+/// Otherwise, chunks are read using the `ChunkLayout` of the parser, which is `Compact` by default.
 pub struct CodeParser<'a, Constant> {
     parsers: &'a ConstantParserTable<'a, Constant>,
+    header: Option<CodeHeader>,
+    layout: ChunkLayout,
 }
 
 impl<'a, Constant> CodeParser<'a, Constant> {
     pub fn new(parsers: &'a ConstantParserTable<'a, Constant>) -> CodeParser<'a, Constant> {
-        CodeParser { parsers }
+        CodeParser {
+            parsers,
+            header: None,
+            layout: ChunkLayout::default(),
+        }
+    }
+    pub fn with_header(self, header: CodeHeader) -> CodeParser<'a, Constant> {
+        CodeParser {
+            header: Some(header),
+            ..self
+        }
+    }
+    pub fn with_layout(self, layout: ChunkLayout) -> CodeParser<'a, Constant> {
+        CodeParser { layout, ..self }
     }
-    pub fn parse(&self, bytes: &RawBytes) -> Result<Code<Constant>, Exception> {
-        let mut chunks: Vec<Chunk<Constant>> = vec![];
+    /// Parses code that is read from `reader` chunk by chunk
+    /// without loading the whole stream into memory first.
+    ///
+    /// The chunks own their code, so the result does not borrow the reader.
+    pub fn parse_reader(&self, reader: impl Read) -> Result<Code<'static, Constant>, Exception> {
+        self.parse(&RawBytes::from_reader(reader))
+            .map(Code::into_owned)
+    }
+    /// Parses code from `bytes`.
+    ///
+    /// If `bytes` are borrowed, the code of the chunks borrows from them as well,
+    /// so only the constants are copied.
+    pub fn parse<'b>(&self, bytes: &RawBytes<'b>) -> Result<Code<'b, Constant>, Exception> {
+        let result = self.parse_code(bytes);
+        match bytes.take_read_error() {
+            Some(error) => Err(Exception::from(ReadFailed(error))),
+            None => result,
+        }
+    }
+    fn parse_code<'b>(&self, bytes: &RawBytes<'b>) -> Result<Code<'b, Constant>, Exception> {
+        let mut chunks: Vec<Chunk<'b, Constant>> = vec![];
         let mut ptr = RawBytesPointer::new();
+        let mut layout = self.layout;
+        if let Some(expected) = self.header {
+            let (actual, header_layout) = CodeHeader::parse(bytes, &mut ptr)?;
+            if actual != expected {
+                return Err(Exception::from(InstructionSetMismatch { expected, actual }));
+            }
+            layout = header_layout;
+        }
         let mut chunk_id: usize = 0;
         while bytes.has_next(&ptr) {
             let chunk = self
-                .parse_chunk(bytes, &mut ptr)
-                .map_err(|err| ChunkParsingError(chunk_id, err))?;
+                .parse_chunk(bytes, &mut ptr, layout)
+                .map_err(|failure| ChunkParsingError {
+                    chunk_id,
+                    constant_id: failure.constant_id,
+                    offset: failure.offset,
+                    snippet: bytes.hex_dump(failure.offset),
+                    cause: failure.cause,
+                })?;
             chunks.push(chunk);
+            bytes.discard_before(&ptr);
             chunk_id += 1;
         }
         if chunks.is_empty() {
@@ -33,48 +89,93 @@ impl<'a, Constant> CodeParser<'a, Constant> {
             Ok(Code { chunks })
         }
     }
-    fn parse_chunk(
+    fn parse_chunk<'b>(
         &self,
-        bytes: &RawBytes,
+        bytes: &RawBytes<'b>,
         ptr: &mut RawBytesPointer,
-    ) -> Result<Chunk<Constant>, Exception> {
+        layout: ChunkLayout,
+    ) -> Result<Chunk<'b, Constant>, ChunkFailure> {
         let mut result_constants: Vec<Constant> = vec![];
-        let n_constants = bytes
-            .read(ptr)
-            .ok_or_else(|| CodeEndedAt("n_constants".to_string()))?;
-        for _ in 0..n_constants {
+        let offset = ptr.next_byte;
+        let n_constants = match layout {
+            ChunkLayout::Compact => bytes.read(ptr).map(u32::from),
+            ChunkLayout::Wide => bytes.read_u32(ptr),
+        }
+        .ok_or_else(|| ChunkFailure::new(offset, None, CodeEndedAt::new("n_constants", offset)))?;
+        for constant_id in 0..n_constants as usize {
+            let offset = ptr.next_byte;
+            let fail = |cause: Exception| ChunkFailure::new(offset, Some(constant_id), cause);
             let constant_type = bytes
                 .read(ptr)
-                .ok_or_else(|| CodeEndedAt("constant_type".to_string()))?;
-            let constant_parser = self
-                .parsers
-                .get_parser(constant_type)
-                .ok_or(UnknownConstantType(constant_type))?;
-            let constant = (constant_parser.parser_fn)(bytes, ptr)
-                .map_err(|e| IllegalConstant(constant_parser.constant_type, e))?;
+                .ok_or_else(|| fail(CodeEndedAt::new("constant_type", offset).into()))?;
+            let constant_parser = self.parsers.get_parser(constant_type).ok_or_else(|| {
+                fail(
+                    UnknownConstantType {
+                        constant_type,
+                        offset,
+                    }
+                    .into(),
+                )
+            })?;
+            let data_offset = ptr.next_byte;
+            let constant = constant_parser.parse(bytes, ptr).map_err(|cause| {
+                ChunkFailure::new(
+                    data_offset,
+                    Some(constant_id),
+                    IllegalConstant {
+                        constant_type,
+                        offset: data_offset,
+                        cause,
+                    },
+                )
+            })?;
             result_constants.push(constant);
         }
-        let n_code_bytes = bytes
-            .read_u16(ptr)
-            .ok_or_else(|| CodeEndedAt("n_code_bytes".to_string()))?;
+        let offset = ptr.next_byte;
+        let n_code_bytes = match layout {
+            ChunkLayout::Compact => bytes.read_u16(ptr).map(u32::from),
+            ChunkLayout::Wide => bytes.read_u32(ptr),
+        }
+        .ok_or_else(|| ChunkFailure::new(offset, None, CodeEndedAt::new("n_code_bytes", offset)))?;
+        let offset = ptr.next_byte;
         let code = bytes
-            .read_n(ptr, usize::from(n_code_bytes))
-            .ok_or_else(|| CodeEndedAt("code".to_string()))?;
+            .read_cow(ptr, n_code_bytes as usize)
+            .ok_or_else(|| ChunkFailure::new(offset, None, CodeEndedAt::new("code", offset)))?;
         Ok(Chunk {
             constants: result_constants,
             code,
+            exception_handlers: vec![],
         })
     }
 }
 
+/// The location and the cause of a chunk parsing failure
+struct ChunkFailure {
+    offset: usize,
+    constant_id: Option<usize>,
+    cause: Exception,
+}
+
+impl ChunkFailure {
+    fn new(offset: usize, constant_id: Option<usize>, cause: impl Into<Exception>) -> ChunkFailure {
+        ChunkFailure {
+            offset,
+            constant_id,
+            cause: cause.into(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::byte_readable::ByteReadable;
     use crate::code::{Chunk, Code};
     use crate::exception::Exception;
+    use crate::parsing::code_header::{ChunkLayout, CodeHeader};
     use crate::parsing::code_parser::CodeParser;
     use crate::parsing::constant_parser::{ConstantParser, ConstantParserTable};
     use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+    use std::borrow::Cow;
 
     const DUMMY_CONSTANT: ConstantParser<u8> = ConstantParser {
         constant_type: 0,
@@ -88,19 +189,19 @@ mod tests {
         Ok(bytes.read(pointer).unwrap())
     }
 
-    fn parse(bytes: Vec<u8>) -> Code<u8> {
+    fn parse(bytes: Vec<u8>) -> Code<'static, u8> {
         let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
         let parser = CodeParser::new(&table);
         parser.parse(&RawBytes::from_bytes(bytes)).unwrap()
     }
 
-    impl PartialEq for Code<u8> {
+    impl PartialEq for Code<'_, u8> {
         fn eq(&self, other: &Self) -> bool {
             self.chunks == other.chunks
         }
     }
 
-    impl PartialEq for Chunk<u8> {
+    impl PartialEq for Chunk<'_, u8> {
         fn eq(&self, other: &Self) -> bool {
             self.constants == other.constants && self.code == other.code
         }
@@ -149,7 +250,7 @@ mod tests {
         let code = parse(bytes);
         assert_eq!(1, code.chunks.len());
         assert_eq!(vec![1, 2, 3], code.chunks.first().unwrap().constants);
-        assert_eq!(vec![4, 5, 6], code.chunks.first().unwrap().code);
+        assert_eq!(vec![4, 5, 6], code.chunks.first().unwrap().code.as_ref());
     }
 
     #[test]
@@ -171,9 +272,126 @@ mod tests {
         let second = actual.chunks.last().unwrap();
 
         assert_eq!(vec![1], first.constants);
-        assert_eq!(vec![10], first.code);
+        assert_eq!(vec![10], first.code.as_ref());
 
         assert_eq!(vec![2], second.constants);
-        assert_eq!(vec![11], second.code);
+        assert_eq!(vec![11], second.code.as_ref());
+    }
+
+    fn parse_with_header(
+        bytes: Vec<u8>,
+        header: CodeHeader,
+    ) -> Result<Code<'static, u8>, Exception> {
+        let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
+        let parser = CodeParser::new(&table).with_header(header);
+        parser.parse(&RawBytes::from_bytes(bytes))
+    }
+
+    #[test]
+    fn test_should_parse_code_with_header() {
+        let mut bytes = vec![];
+        CodeHeader::new(7, 1).write(ChunkLayout::Compact, &mut bytes);
+        bytes.extend(vec![1, 0, 1, 1, 0, 10]);
+        let code = parse_with_header(bytes, CodeHeader::new(7, 1)).unwrap();
+        assert_eq!(vec![1], code.chunks[0].constants);
+        assert_eq!(vec![10], code.chunks[0].code.as_ref());
+    }
+
+    #[test]
+    fn test_should_reject_code_for_another_instruction_set() {
+        let mut bytes = vec![];
+        CodeHeader::new(7, 2).write(ChunkLayout::Compact, &mut bytes);
+        bytes.extend(vec![0, 0, 0]);
+        let error = parse_with_header(bytes, CodeHeader::new(7, 1)).unwrap_err();
+        assert_eq!("InstructionSetMismatch", error.name);
+    }
+
+    #[test]
+    fn test_should_reject_code_without_header() {
+        let error = parse_with_header(vec![0, 0, 0], CodeHeader::new(7, 1)).unwrap_err();
+        assert_eq!("CodeEndedAt", error.name);
+    }
+
+    #[test]
+    fn test_should_parse_wide_layout() {
+        let bytes = vec![
+            1, 0, 0, 0, // 1 constant
+            0, 1, // constant
+            2, 0, 0, 0, // 2 instructions
+            10, 11,
+        ];
+        let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
+        let parser = CodeParser::new(&table).with_layout(ChunkLayout::Wide);
+        let code = parser.parse(&RawBytes::from_bytes(bytes)).unwrap();
+        assert_eq!(vec![1], code.chunks[0].constants);
+        assert_eq!(vec![10, 11], code.chunks[0].code.as_ref());
+    }
+
+    #[test]
+    fn test_should_use_layout_from_header() {
+        let mut bytes = vec![];
+        CodeHeader::new(7, 1).write(ChunkLayout::Wide, &mut bytes);
+        bytes.extend(vec![0, 0, 0, 0, 1, 0, 0, 0, 10]);
+        let code = parse_with_header(bytes, CodeHeader::new(7, 1)).unwrap();
+        assert_eq!(vec![10], code.chunks[0].code.as_ref());
+    }
+
+    #[test]
+    fn test_should_parse_reader() {
+        let bytes = vec![
+            1, 0, 1, 2, 0, 10, 11, // chunk 0
+            0, 1, 0, 12, // chunk 1
+        ];
+        let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
+        let mut reader = std::io::Cursor::new(bytes);
+        let code = CodeParser::new(&table).parse_reader(&mut reader).unwrap();
+        drop(reader);
+        assert_eq!(vec![1], code.chunks[0].constants);
+        assert_eq!(vec![12], code.chunks[1].code.as_ref());
+    }
+
+    #[test]
+    fn test_read_error_should_fail_parsing() {
+        struct FailingReader;
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection reset"))
+            }
+        }
+        let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
+        let error = CodeParser::new(&table)
+            .parse_reader(FailingReader)
+            .unwrap_err();
+        assert_eq!("ReadFailed", error.name);
+    }
+
+    #[test]
+    fn test_code_parsed_from_slice_should_be_borrowed() {
+        let bytes = vec![1, 0, 1, 2, 0, 10, 11];
+        let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
+        let code = CodeParser::new(&table)
+            .parse(&RawBytes::from_slice(&bytes))
+            .unwrap();
+        assert!(matches!(code.chunks[0].code, Cow::Borrowed(_)));
+        assert_eq!(bytes[5..].as_ptr(), code.chunks[0].code.as_ptr());
+    }
+
+    #[test]
+    fn test_parsing_error_should_include_location() {
+        let bytes = vec![
+            0, 0, 0, // chunk 0
+            2, 0, 1, 7, 0, 0, // chunk 1 with an unknown constant type
+        ];
+        let table = ConstantParserTable::parsers(&[DUMMY_CONSTANT]);
+        let error = CodeParser::new(&table)
+            .parse(&RawBytes::from_bytes(bytes))
+            .unwrap_err();
+        assert_eq!("ChunkParsingError", error.name);
+        assert!(error
+            .message
+            .starts_with("Could not parse chunk #1 constant #1 at byte 6:"));
+        assert!(error
+            .message
+            .ends_with("00000000: 00 00 00 02 00 01 [07] 00 00"));
     }
 }
@@ -16,25 +16,119 @@ pub struct ConstantParser<Constant> {
 /// A function that decodes the value of the constant.
 ///
 /// The encoded value starts at a position that is marked by `RawBytesPointer` in `RawBytes`.
-type ConstantParserFn<Constant> =
+pub type ConstantParserFn<Constant> =
     fn(&RawBytes, &mut RawBytesPointer) -> Result<Constant, Exception>;
 
+/// Decodes the value of a constant.
+///
+/// Unlike `ConstantParserFn`, a parser can hold configuration or shared state,
+/// e.g. a string interner. Since parsers are shared, mutable state requires interior mutability.
+/// Functions and closures with the signature of `ConstantParserFn` implement this trait.
+pub trait ParseConstant<Constant> {
+    fn parse(&self, bytes: &RawBytes, ptr: &mut RawBytesPointer) -> Result<Constant, Exception>;
+}
+
+impl<Constant, F> ParseConstant<Constant> for F
+where
+    F: Fn(&RawBytes, &mut RawBytesPointer) -> Result<Constant, Exception>,
+{
+    fn parse(&self, bytes: &RawBytes, ptr: &mut RawBytesPointer) -> Result<Constant, Exception> {
+        self(bytes, ptr)
+    }
+}
+
 /// A set of constant parsers
 pub struct ConstantParserTable<'a, Constant> {
-    parsers: HashMap<u8, &'a ConstantParser<Constant>>,
+    parsers: HashMap<u8, Box<dyn ParseConstant<Constant> + 'a>>,
 }
 
 impl<'a, Constant> ConstantParserTable<'a, Constant> {
+    pub fn new() -> ConstantParserTable<'a, Constant> {
+        ConstantParserTable {
+            parsers: HashMap::new(),
+        }
+    }
     pub fn parsers(parsers: &'a [ConstantParser<Constant>]) -> ConstantParserTable<'a, Constant> {
-        let mut parsers_result: HashMap<u8, &'a ConstantParser<Constant>> = HashMap::new();
+        let mut table = ConstantParserTable::new();
         for parser in parsers {
-            parsers_result.insert(parser.constant_type, parser);
+            table.add_parser(parser.constant_type, parser.parser_fn);
         }
-        ConstantParserTable {
-            parsers: parsers_result,
+        table
+    }
+    /// Registers the parser of constants with the given type, replacing the previous one.
+    pub fn add_parser(
+        &mut self,
+        constant_type: u8,
+        parser: impl ParseConstant<Constant> + 'a,
+    ) -> &mut ConstantParserTable<'a, Constant> {
+        self.parsers.insert(constant_type, Box::new(parser));
+        self
+    }
+    pub fn get_parser(&self, constant_type: u8) -> Option<&(dyn ParseConstant<Constant> + 'a)> {
+        self.parsers
+            .get(&constant_type)
+            .map(|parser| parser.as_ref())
+    }
+}
+
+impl<'a, Constant> Default for ConstantParserTable<'a, Constant> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_readable::ByteReadable;
+    use crate::exception::Exception;
+    use crate::parsing::constant_parser::{ConstantParserTable, ParseConstant};
+    use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+    use crate::parsing::CodeParser;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    /// Returns the same `Rc` for equal strings
+    #[derive(Default)]
+    struct Interner {
+        strings: RefCell<HashSet<Rc<str>>>,
+    }
+
+    impl ParseConstant<Rc<str>> for Interner {
+        fn parse(&self, bytes: &RawBytes, ptr: &mut RawBytesPointer) -> Result<Rc<str>, Exception> {
+            let string: Rc<str> = Rc::from(bytes.read_string(ptr).unwrap());
+            let mut strings = self.strings.borrow_mut();
+            if let Some(interned) = strings.get(&string) {
+                return Ok(Rc::clone(interned));
+            }
+            strings.insert(Rc::clone(&string));
+            Ok(string)
         }
     }
-    pub fn get_parser(&self, constant_type: u8) -> Option<&ConstantParser<Constant>> {
-        self.parsers.get(&constant_type).cloned()
+
+    #[test]
+    fn stateful_parser_should_share_state_between_constants() {
+        let mut table = ConstantParserTable::new();
+        table.add_parser(0, Interner::default());
+        let bytes = vec![2, 0, 1, b'a', 0, 1, b'a', 0, 0];
+        let code = CodeParser::new(&table)
+            .parse(&RawBytes::from_bytes(bytes))
+            .unwrap();
+        let constants = &code.chunks[0].constants;
+        assert!(Rc::ptr_eq(&constants[0], &constants[1]));
+    }
+
+    #[test]
+    fn closures_should_be_parsers() {
+        let offset = 10;
+        let mut table = ConstantParserTable::new();
+        table.add_parser(0, |bytes: &RawBytes, ptr: &mut RawBytesPointer| {
+            Ok(bytes.read(ptr).unwrap() + offset)
+        });
+        let bytes = vec![1, 0, 5, 0, 0];
+        let code = CodeParser::new(&table)
+            .parse(&RawBytes::from_bytes(bytes))
+            .unwrap();
+        assert_eq!(vec![15], code.chunks[0].constants);
     }
 }
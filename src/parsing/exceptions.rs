@@ -1,4 +1,5 @@
 use crate::exception::{Exception, ExceptionType};
+use crate::parsing::code_header::CodeHeader;
 
 pub struct EmptyCode;
 
@@ -12,43 +13,112 @@ impl From<EmptyCode> for Exception {
     }
 }
 
-pub struct ChunkParsingError(pub usize, pub Exception);
+/// Parsing of a chunk failed at the absolute byte `offset`.
+///
+/// `constant_id` is set if the failure happened while reading a constant
+/// and `snippet` is a hex dump of the bytes around `offset`.
+pub struct ChunkParsingError {
+    pub chunk_id: usize,
+    pub constant_id: Option<usize>,
+    pub offset: usize,
+    pub snippet: String,
+    pub cause: Exception,
+}
 
 impl From<ChunkParsingError> for Exception {
     fn from(error: ChunkParsingError) -> Self {
+        let constant = match error.constant_id {
+            Some(constant_id) => format!(" constant #{}", constant_id),
+            None => String::new(),
+        };
         Exception {
             exception_type: ExceptionType::Static,
             name: "ChunkParsingError".to_string(),
-            message: format!("Could not parse chunk #{}: {}", error.0, error.1),
+            message: format!(
+                "Could not parse chunk #{}{} at byte {}: {}\n{}",
+                error.chunk_id, constant, error.offset, error.cause, error.snippet
+            ),
         }
     }
 }
 
-pub struct CodeEndedAt(pub String);
+/// The code ended while reading the value that starts at byte `offset`
+pub struct CodeEndedAt {
+    pub reading: String,
+    pub offset: usize,
+}
+
+impl CodeEndedAt {
+    pub fn new(reading: &str, offset: usize) -> CodeEndedAt {
+        CodeEndedAt {
+            reading: reading.to_string(),
+            offset,
+        }
+    }
+}
 
 impl From<CodeEndedAt> for Exception {
     fn from(exception: CodeEndedAt) -> Self {
         Exception {
             exception_type: ExceptionType::Static,
             name: "CodeEndedAt".to_string(),
-            message: format!("Code ended while reading {}", exception.0),
+            message: format!(
+                "Code ended while reading {} that starts at byte {}",
+                exception.reading, exception.offset
+            ),
         }
     }
 }
 
-pub struct UnknownConstantType(pub u8);
+pub struct ReadFailed(pub std::io::Error);
+
+impl From<ReadFailed> for Exception {
+    fn from(exception: ReadFailed) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "ReadFailed".to_string(),
+            message: format!("Could not read code: {}", exception.0),
+        }
+    }
+}
+
+/// The data of a constant has the right size but an invalid value
+pub struct InvalidConstantData(pub String);
+
+impl From<InvalidConstantData> for Exception {
+    fn from(exception: InvalidConstantData) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "InvalidConstantData".to_string(),
+            message: exception.0,
+        }
+    }
+}
+
+pub struct UnknownConstantType {
+    pub constant_type: u8,
+    pub offset: usize,
+}
 
 impl From<UnknownConstantType> for Exception {
     fn from(exception: UnknownConstantType) -> Self {
         Exception {
             exception_type: ExceptionType::Static,
             name: "UnknownConstantType".to_string(),
-            message: format!("Unknown constant with type {}", exception.0),
+            message: format!(
+                "Unknown constant with type {} at byte {}",
+                exception.constant_type, exception.offset
+            ),
         }
     }
 }
 
-pub struct IllegalConstant(pub u8, pub Exception);
+/// The constant parser failed to parse the data that starts at byte `offset`
+pub struct IllegalConstant {
+    pub constant_type: u8,
+    pub offset: usize,
+    pub cause: Exception,
+}
 
 impl From<IllegalConstant> for Exception {
     fn from(exception: IllegalConstant) -> Self {
@@ -56,8 +126,56 @@ impl From<IllegalConstant> for Exception {
             exception_type: ExceptionType::Static,
             name: "IllegalConstant".to_string(),
             message: format!(
-                "Could not parse constant with type {}: {}",
-                exception.0, exception.1
+                "Could not parse constant with type {} at byte {}: {}",
+                exception.constant_type, exception.offset, exception.cause
+            ),
+        }
+    }
+}
+
+pub struct InvalidMagicNumber(pub Vec<u8>);
+
+impl From<InvalidMagicNumber> for Exception {
+    fn from(exception: InvalidMagicNumber) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "InvalidMagicNumber".to_string(),
+            message: format!(
+                "Code starts with {:02x?} instead of a magic number",
+                exception.0
+            ),
+        }
+    }
+}
+
+pub struct UnsupportedFormatVersion(pub u16);
+
+impl From<UnsupportedFormatVersion> for Exception {
+    fn from(exception: UnsupportedFormatVersion) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "UnsupportedFormatVersion".to_string(),
+            message: format!("Bytecode format version {} is not supported", exception.0),
+        }
+    }
+}
+
+pub struct InstructionSetMismatch {
+    pub expected: CodeHeader,
+    pub actual: CodeHeader,
+}
+
+impl From<InstructionSetMismatch> for Exception {
+    fn from(exception: InstructionSetMismatch) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "InstructionSetMismatch".to_string(),
+            message: format!(
+                "Expected instruction set {} version {} but code was compiled for instruction set {} version {}",
+                exception.expected.instruction_set_id,
+                exception.expected.instruction_set_version,
+                exception.actual.instruction_set_id,
+                exception.actual.instruction_set_version
             ),
         }
     }
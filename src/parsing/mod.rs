@@ -1,8 +1,11 @@
+pub use code_header::{ChunkLayout, CodeHeader, MAGIC_NUMBER};
 pub use code_parser::CodeParser;
-pub use constant_parser::{ConstantParser, ConstantParserTable};
+pub use constant_parser::{ConstantParser, ConstantParserFn, ConstantParserTable, ParseConstant};
 pub use raw_bytes::{RawBytes, RawBytesPointer};
 
+mod code_header;
 mod code_parser;
 mod constant_parser;
 pub mod exceptions;
 mod raw_bytes;
+pub mod std_constants;
@@ -1,6 +1,14 @@
-use crate::byte_readable::ByteReadable;
+use crate::byte_readable::{ByteOrder, ByteReadable};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::fs;
-use std::io::Error;
+use std::io::{Error, ErrorKind, Read};
+
+/// The number of bytes that are requested from a reader at once
+const READ_BLOCK_SIZE: usize = 8 * 1024;
+
+/// The number of bytes in a row of `RawBytes::hex_dump`
+const HEX_DUMP_ROW: usize = 16;
 
 /// The current reading location of `RawBytes`
 pub struct RawBytesPointer {
@@ -20,28 +28,249 @@ impl Default for RawBytesPointer {
 }
 
 /// A vector of bytes
-pub struct RawBytes {
-    data: Vec<u8>,
+///
+/// The bytes are either given up front (owned or borrowed)
+/// or lazily pulled from a reader as they are needed.
+/// Bytes that were pulled from a reader are buffered until `discard_before` is called.
+///
+/// Multi-byte values are little-endian unless another byte order is set with `with_byte_order`.
+pub struct RawBytes<'b> {
+    data: RefCell<Cow<'b, [u8]>>,
+    // position of `data[0]` in the whole stream
+    offset: Cell<usize>,
+    reader: Option<RefCell<Box<dyn Read + 'b>>>,
+    finished: Cell<bool>,
+    read_error: RefCell<Option<Error>>,
+    byte_order: ByteOrder,
 }
 
-impl RawBytes {
-    pub fn from_file(path: &str) -> Result<RawBytes, Error> {
+impl RawBytes<'static> {
+    pub fn from_file(path: &str) -> Result<RawBytes<'static>, Error> {
         let data = fs::read(path)?;
-        Ok(RawBytes { data })
+        Ok(RawBytes::from_bytes(data))
+    }
+    pub fn from_bytes(bytes: Vec<u8>) -> RawBytes<'static> {
+        RawBytes::from_data(Cow::Owned(bytes))
+    }
+}
+
+impl<'b> RawBytes<'b> {
+    /// Creates bytes that are read from `reader` on demand.
+    ///
+    /// The reader may borrow, e.g. `&mut TcpStream` or a decompressor over `&[u8]`.
+    pub fn from_reader(reader: impl Read + 'b) -> RawBytes<'b> {
+        RawBytes {
+            data: RefCell::new(Cow::Owned(vec![])),
+            offset: Cell::new(0),
+            reader: Some(RefCell::new(Box::new(reader))),
+            finished: Cell::new(false),
+            read_error: RefCell::new(None),
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    /// Borrows the bytes, e.g. a slice of a memory-mapped file.
+    ///
+    /// Code that is parsed from these bytes borrows its chunks instead of copying them.
+    pub fn from_slice(bytes: &'b [u8]) -> RawBytes<'b> {
+        RawBytes::from_data(Cow::Borrowed(bytes))
+    }
+
+    fn from_data(data: Cow<'b, [u8]>) -> RawBytes<'b> {
+        RawBytes {
+            data: RefCell::new(data),
+            offset: Cell::new(0),
+            reader: None,
+            finished: Cell::new(true),
+            read_error: RefCell::new(None),
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    pub fn with_byte_order(self, byte_order: ByteOrder) -> RawBytes<'b> {
+        RawBytes { byte_order, ..self }
+    }
+
+    /// Reads `n` bytes, borrowing them if the underlying bytes are borrowed.
+    pub fn read_cow(&self, ptr: &mut RawBytesPointer, n: usize) -> Option<Cow<'b, [u8]>> {
+        if let Cow::Borrowed(data) = &*self.data.borrow() {
+            let data: &'b [u8] = data;
+            let end = ptr.next_byte.checked_add(n)?;
+            let result = data.get(ptr.next_byte..end)?;
+            ptr.next_byte = end;
+            return Some(Cow::Borrowed(result));
+        }
+        self.read_n(ptr, n).map(Cow::Owned)
+    }
+
+    /// Renders the rows of 16 bytes around `offset` in hex, marking the byte at `offset`.
+    ///
+    /// Discarded bytes are shown as `..` and the end of the bytes at `offset` as `[EOF]`.
+    pub fn hex_dump(&self, offset: usize) -> String {
+        let first_row = (offset / HEX_DUMP_ROW).saturating_sub(1) * HEX_DUMP_ROW;
+        let mut rows: Vec<String> = vec![];
+        for row_start in (first_row..offset + HEX_DUMP_ROW + 1).step_by(HEX_DUMP_ROW) {
+            let mut cells: Vec<String> = vec![];
+            for index in row_start..row_start + HEX_DUMP_ROW {
+                let cell = match self.get(index) {
+                    Some(byte) => format!("{:02x}", byte),
+                    None if index < self.offset.get() => "..".to_string(),
+                    None if index == offset => "[EOF]".to_string(),
+                    None => break,
+                };
+                if index == offset && cell != "[EOF]" {
+                    cells.push(format!("[{}]", cell));
+                } else {
+                    cells.push(cell);
+                }
+            }
+            if !cells.is_empty() {
+                rows.push(format!("{:08x}: {}", row_start, cells.join(" ")));
+            }
+        }
+        rows.join("\n")
+    }
+
+    /// Forgets the bytes of a reader that are located before `ptr`.
+    ///
+    /// Reading them afterwards returns `None`. Bytes given up front are never discarded.
+    pub fn discard_before(&self, ptr: &RawBytesPointer) {
+        if self.reader.is_none() {
+            return;
+        }
+        let offset = self.offset.get();
+        if ptr.next_byte <= offset {
+            return;
+        }
+        let mut data = self.data.borrow_mut();
+        let n = (ptr.next_byte - offset).min(data.len());
+        data.to_mut().drain(..n);
+        self.offset.set(offset + n);
+    }
+
+    /// Returns the error that stopped reading from the reader.
+    pub fn take_read_error(&self) -> Option<Error> {
+        self.read_error.borrow_mut().take()
     }
-    pub fn from_bytes(bytes: Vec<u8>) -> RawBytes {
-        RawBytes { data: bytes }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        let offset = self.offset.get();
+        if index < offset {
+            return None;
+        }
+        let index = index - offset;
+        self.fill(index + 1);
+        self.data.borrow().get(index).cloned()
+    }
+
+    /// Reads from the reader until `data` has `len` bytes or the reader is finished.
+    fn fill(&self, len: usize) {
+        let reader = match &self.reader {
+            Some(reader) => reader,
+            None => return,
+        };
+        let mut reader = reader.borrow_mut();
+        let mut data = self.data.borrow_mut();
+        let mut block = [0; READ_BLOCK_SIZE];
+        while data.len() < len && !self.finished.get() {
+            match reader.read(&mut block) {
+                Ok(0) => self.finished.set(true),
+                Ok(n) => data.to_mut().extend(&block[..n]),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => {
+                    self.finished.set(true);
+                    *self.read_error.borrow_mut() = Some(error);
+                }
+            }
+        }
     }
 }
 
-impl ByteReadable<RawBytesPointer> for RawBytes {
+impl<'b> ByteReadable<RawBytesPointer> for RawBytes<'b> {
     fn read(&self, ptr: &mut RawBytesPointer) -> Option<u8> {
-        let result = self.data.get(ptr.next_byte).cloned();
+        let result = self.get(ptr.next_byte);
         ptr.next_byte += 1;
         result
     }
 
     fn has_next(&self, ptr: &RawBytesPointer) -> bool {
-        ptr.next_byte < self.data.len()
+        self.get(ptr.next_byte).is_some()
+    }
+
+    fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_readable::ByteReadable;
+    use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+    use std::borrow::Cow;
+    use std::io::{Cursor, Error, ErrorKind, Read};
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(Error::new(ErrorKind::BrokenPipe, "broken"))
+        }
+    }
+
+    #[test]
+    fn bytes_should_be_read_from_reader() {
+        let bytes = RawBytes::from_reader(Cursor::new(vec![1, 2, 0, 3]));
+        let mut ptr = RawBytesPointer::new();
+        assert_eq!(Some(1), bytes.read(&mut ptr));
+        assert_eq!(Some(vec![2, 0, 3]), bytes.read_n(&mut ptr, 3));
+        assert!(!bytes.has_next(&ptr));
+        assert_eq!(None, bytes.read(&mut ptr));
+    }
+
+    #[test]
+    fn discarded_bytes_should_not_be_readable() {
+        let bytes = RawBytes::from_reader(Cursor::new(vec![1, 2, 3]));
+        let mut ptr = RawBytesPointer::new();
+        bytes.read_n(&mut ptr, 2);
+        bytes.discard_before(&ptr);
+        assert_eq!(Some(3), bytes.read(&mut ptr));
+        assert_eq!(None, bytes.read(&mut RawBytesPointer::new()));
+    }
+
+    #[test]
+    fn read_errors_should_be_recorded() {
+        let bytes = RawBytes::from_reader(FailingReader);
+        assert!(!bytes.has_next(&RawBytesPointer::new()));
+        assert_eq!(
+            ErrorKind::BrokenPipe,
+            bytes.take_read_error().unwrap().kind()
+        );
+    }
+
+    #[test]
+    fn read_cow_should_borrow_slices() {
+        let data = vec![1, 2, 3];
+        let bytes = RawBytes::from_slice(&data);
+        let mut ptr = RawBytesPointer::new();
+        bytes.read(&mut ptr);
+        let result = bytes.read_cow(&mut ptr, 2).unwrap();
+        assert!(matches!(result, Cow::Borrowed(&[2, 3])));
+        assert_eq!(None, bytes.read_cow(&mut ptr, 1));
+    }
+
+    #[test]
+    fn hex_dump_should_mark_offset() {
+        let bytes = RawBytes::from_bytes((0..40).collect());
+        assert_eq!(
+            "00000000: 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n\
+             00000010: 10 11 [12] 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f\n\
+             00000020: 20 21 22 23 24 25 26 27",
+            bytes.hex_dump(18)
+        );
+        assert_eq!(
+            "00000010: 10 11 12 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f\n\
+             00000020: 20 21 22 23 24 25 26 27 [EOF]",
+            bytes.hex_dump(40)
+        );
     }
 }
@@ -0,0 +1,260 @@
+//! Ready-made parsers for common constants.
+//!
+//! Each constructor accepts the `constant_type` byte and returns a `ConstantParser`
+//! that converts the parsed value into the user's `Constant` with `From`.
+//! Multi-byte values are read in the byte order of `RawBytes`.
+//!
+//! | Parser      | Value      | Encoding                                                   |
+//! |-------------|------------|------------------------------------------------------------|
+//! | `int`       | `i64`      | 8 bytes                                                    |
+//! | `float`     | `f64`      | 8 bytes, IEEE 754                                          |
+//! | `bool`      | `bool`     | 1 byte, `0` is `false` and `1` is `true`                   |
+//! | `nil`       | `Nil`      | no data                                                    |
+//! | `string`    | `String`   | length in bytes as unsigned LEB128, then UTF-8 bytes       |
+//! | `chunk_ref` | `ChunkRef` | chunk id as unsigned LEB128                                |
+//!
+//! ```ignore
+//! const PARSERS: [ConstantParser<Constant>; 3] = [
+//!     std_constants::int(0),
+//!     std_constants::string(1),
+//!     std_constants::chunk_ref(2),
+//! ];
+//! ```
+
+use crate::byte_readable::ByteReadable;
+use crate::exception::Exception;
+use crate::parsing::constant_parser::ConstantParser;
+use crate::parsing::exceptions::{CodeEndedAt, InvalidConstantData};
+use crate::parsing::raw_bytes::{RawBytes, RawBytesPointer};
+use std::convert::TryFrom;
+
+/// The value of a `nil` constant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nil;
+
+/// A reference to a chunk, e.g. a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRef(pub usize);
+
+pub const fn int<Constant: From<i64>>(constant_type: u8) -> ConstantParser<Constant> {
+    ConstantParser {
+        constant_type,
+        parser_fn: parse_int,
+    }
+}
+
+pub const fn float<Constant: From<f64>>(constant_type: u8) -> ConstantParser<Constant> {
+    ConstantParser {
+        constant_type,
+        parser_fn: parse_float,
+    }
+}
+
+pub const fn bool<Constant: From<bool>>(constant_type: u8) -> ConstantParser<Constant> {
+    ConstantParser {
+        constant_type,
+        parser_fn: parse_bool,
+    }
+}
+
+pub const fn nil<Constant: From<Nil>>(constant_type: u8) -> ConstantParser<Constant> {
+    ConstantParser {
+        constant_type,
+        parser_fn: parse_nil,
+    }
+}
+
+pub const fn string<Constant: From<String>>(constant_type: u8) -> ConstantParser<Constant> {
+    ConstantParser {
+        constant_type,
+        parser_fn: parse_string,
+    }
+}
+
+pub const fn chunk_ref<Constant: From<ChunkRef>>(constant_type: u8) -> ConstantParser<Constant> {
+    ConstantParser {
+        constant_type,
+        parser_fn: parse_chunk_ref,
+    }
+}
+
+fn parse_int<Constant: From<i64>>(
+    bytes: &RawBytes,
+    ptr: &mut RawBytesPointer,
+) -> Result<Constant, Exception> {
+    let offset = ptr.next_byte;
+    let value = bytes
+        .read_i64(ptr)
+        .ok_or_else(|| CodeEndedAt::new("int", offset))?;
+    Ok(Constant::from(value))
+}
+
+fn parse_float<Constant: From<f64>>(
+    bytes: &RawBytes,
+    ptr: &mut RawBytesPointer,
+) -> Result<Constant, Exception> {
+    let offset = ptr.next_byte;
+    let value = bytes
+        .read_f64(ptr)
+        .ok_or_else(|| CodeEndedAt::new("float", offset))?;
+    Ok(Constant::from(value))
+}
+
+fn parse_bool<Constant: From<bool>>(
+    bytes: &RawBytes,
+    ptr: &mut RawBytesPointer,
+) -> Result<Constant, Exception> {
+    let offset = ptr.next_byte;
+    match bytes.read(ptr) {
+        Some(0) => Ok(Constant::from(false)),
+        Some(1) => Ok(Constant::from(true)),
+        Some(byte) => Err(Exception::from(InvalidConstantData(format!(
+            "{} is not a bool",
+            byte
+        )))),
+        None => Err(Exception::from(CodeEndedAt::new("bool", offset))),
+    }
+}
+
+fn parse_nil<Constant: From<Nil>>(
+    _: &RawBytes,
+    _: &mut RawBytesPointer,
+) -> Result<Constant, Exception> {
+    Ok(Constant::from(Nil))
+}
+
+fn parse_string<Constant: From<String>>(
+    bytes: &RawBytes,
+    ptr: &mut RawBytesPointer,
+) -> Result<Constant, Exception> {
+    let offset = ptr.next_byte;
+    let data = bytes
+        .read_bytes(ptr)
+        .ok_or_else(|| CodeEndedAt::new("string", offset))?;
+    let value = String::from_utf8(data)
+        .map_err(|error| InvalidConstantData(format!("string is not UTF-8: {}", error)))?;
+    Ok(Constant::from(value))
+}
+
+fn parse_chunk_ref<Constant: From<ChunkRef>>(
+    bytes: &RawBytes,
+    ptr: &mut RawBytesPointer,
+) -> Result<Constant, Exception> {
+    let offset = ptr.next_byte;
+    let chunk_id = bytes
+        .read_uleb128(ptr)
+        .ok_or_else(|| CodeEndedAt::new("chunk_ref", offset))?;
+    let chunk_id = usize::try_from(chunk_id)
+        .map_err(|_| InvalidConstantData(format!("chunk #{} does not fit into usize", chunk_id)))?;
+    Ok(Constant::from(ChunkRef(chunk_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_readable::ByteOrder;
+    use crate::parsing::std_constants::{self, ChunkRef, Nil};
+    use crate::parsing::{CodeParser, ConstantParser, ConstantParserTable, RawBytes};
+
+    #[derive(Debug, PartialEq)]
+    enum Constant {
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+        Nil,
+        String(String),
+        Function(usize),
+    }
+
+    impl From<i64> for Constant {
+        fn from(value: i64) -> Self {
+            Constant::Int(value)
+        }
+    }
+
+    impl From<f64> for Constant {
+        fn from(value: f64) -> Self {
+            Constant::Float(value)
+        }
+    }
+
+    impl From<bool> for Constant {
+        fn from(value: bool) -> Self {
+            Constant::Bool(value)
+        }
+    }
+
+    impl From<Nil> for Constant {
+        fn from(_: Nil) -> Self {
+            Constant::Nil
+        }
+    }
+
+    impl From<String> for Constant {
+        fn from(value: String) -> Self {
+            Constant::String(value)
+        }
+    }
+
+    impl From<ChunkRef> for Constant {
+        fn from(value: ChunkRef) -> Self {
+            Constant::Function(value.0)
+        }
+    }
+
+    const PARSERS: [ConstantParser<Constant>; 6] = [
+        std_constants::int(0),
+        std_constants::float(1),
+        std_constants::bool(2),
+        std_constants::nil(3),
+        std_constants::string(4),
+        std_constants::chunk_ref(5),
+    ];
+
+    fn parse_constants(constants: Vec<u8>, byte_order: ByteOrder) -> Vec<Constant> {
+        let mut bytes = constants;
+        bytes.extend(vec![0, 0]);
+        let table = ConstantParserTable::parsers(&PARSERS);
+        let bytes = RawBytes::from_bytes(bytes).with_byte_order(byte_order);
+        let mut code = CodeParser::new(&table).parse(&bytes).unwrap();
+        code.chunks.remove(0).constants
+    }
+
+    #[test]
+    fn constant_pool_should_be_parsed() {
+        let mut bytes = vec![6];
+        bytes.push(0);
+        bytes.extend(&(-7i64).to_le_bytes());
+        bytes.push(1);
+        bytes.extend(&2.5f64.to_le_bytes());
+        bytes.extend(vec![2, 1, 3, 4, 2, b'h', b'i', 5, 0x81, 0x01]);
+        assert_eq!(
+            vec![
+                Constant::Int(-7),
+                Constant::Float(2.5),
+                Constant::Bool(true),
+                Constant::Nil,
+                Constant::String("hi".to_string()),
+                Constant::Function(129),
+            ],
+            parse_constants(bytes, ByteOrder::LittleEndian)
+        );
+    }
+
+    #[test]
+    fn numbers_should_be_read_in_byte_order() {
+        let mut bytes = vec![1, 0];
+        bytes.extend(&300i64.to_be_bytes());
+        assert_eq!(
+            vec![Constant::Int(300)],
+            parse_constants(bytes, ByteOrder::BigEndian)
+        );
+    }
+
+    #[test]
+    fn invalid_bool_should_not_be_parsed() {
+        let table = ConstantParserTable::parsers(&PARSERS);
+        let bytes = RawBytes::from_bytes(vec![1, 2, 5, 0, 0]);
+        let error = CodeParser::new(&table).parse(&bytes).unwrap_err();
+        assert!(error.message.contains("5 is not a bool"));
+    }
+}
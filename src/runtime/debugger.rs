@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+use crate::exception::Exception;
+use crate::runtime::call_frame::CallFrame;
+use crate::runtime::instruction_pointer::InstructionPointer;
+use crate::runtime::machine::{Machine, StepStatus};
+
+/// The reason why the `Debugger` paused the execution
+#[derive(Debug)]
+pub enum Pause {
+    /// The next instruction is located at a breakpoint
+    Breakpoint(InstructionPointer),
+    /// The requested step was completed
+    Stepped,
+    /// There is no code left to execute
+    Halted,
+    /// An instruction raised an exception
+    Errored(Exception),
+}
+
+/// Runs the `Machine` step by step and pauses at breakpoints.
+///
+/// A breakpoint is a location in code (`InstructionPointer`).
+/// The debugger pauses before running an instruction that is located at a breakpoint.
+/// While the execution is paused the state of the machine can be inspected.
+pub struct Debugger<'m, 'a, Constant, Value: Debug> {
+    machine: &'m mut Machine<'a, Constant, Value>,
+    breakpoints: HashSet<InstructionPointer>,
+    /// The location where the execution was paused last
+    paused_at: Option<InstructionPointer>,
+}
+
+impl<'m, 'a, Constant, Value: Debug> Debugger<'m, 'a, Constant, Value> {
+    pub fn new(machine: &'m mut Machine<'a, Constant, Value>) -> Debugger<'m, 'a, Constant, Value> {
+        Debugger {
+            machine,
+            breakpoints: HashSet::new(),
+            paused_at: None,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, chunk_id: usize, offset: usize) {
+        self.breakpoints.insert(InstructionPointer {
+            chunk_id,
+            instruction_pointer: offset,
+        });
+    }
+
+    /// Removes the breakpoint and returns `true` if it existed.
+    pub fn remove_breakpoint(&mut self, chunk_id: usize, offset: usize) -> bool {
+        self.breakpoints.remove(&InstructionPointer {
+            chunk_id,
+            instruction_pointer: offset,
+        })
+    }
+
+    pub fn breakpoints(&self) -> &HashSet<InstructionPointer> {
+        &self.breakpoints
+    }
+
+    /// Runs until the next breakpoint is reached.
+    pub fn resume(&mut self) -> Pause {
+        self.run_while(|_| true)
+    }
+
+    /// Runs exactly one instruction.
+    pub fn step_into(&mut self) -> Pause {
+        self.run_while(|_| false)
+    }
+
+    /// Runs one instruction and, if it called a function, runs until that function returns.
+    pub fn step_over(&mut self) -> Pause {
+        let depth = self.call_depth();
+        self.run_while(|current_depth| current_depth > depth)
+    }
+
+    /// Runs until the current function returns.
+    pub fn step_out(&mut self) -> Pause {
+        let depth = self.call_depth();
+        self.run_while(|current_depth| current_depth >= depth)
+    }
+
+    pub fn machine(&self) -> &Machine<'a, Constant, Value> {
+        self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine<'a, Constant, Value> {
+        self.machine
+    }
+
+    /// Returns the location of the next instruction.
+    pub fn instruction_pointer(&self) -> Option<&InstructionPointer> {
+        self.machine
+            .peek_frame()
+            .ok()
+            .map(|frame| &frame.instruction_pointer)
+    }
+
+    pub fn operands(&self) -> &[Value] {
+        self.machine.operands()
+    }
+
+    pub fn frames(&self) -> &[CallFrame] {
+        self.machine.frames()
+    }
+
+    pub fn globals(&self) -> &HashMap<String, Value> {
+        &self.machine.globals
+    }
+
+    pub fn call_depth(&self) -> usize {
+        self.machine.frames().len()
+    }
+
+    /// Runs at least one instruction and continues while `should_continue(call_depth)` holds
+    /// and no breakpoint is reached.
+    ///
+    /// Nothing is run if the next instruction is located at a breakpoint
+    /// unless the debugger is already paused there.
+    fn run_while(&mut self, should_continue: impl Fn(usize) -> bool) -> Pause {
+        let pause = self.pause_while(should_continue);
+        self.paused_at = self.instruction_pointer().cloned();
+        pause
+    }
+
+    fn pause_while(&mut self, should_continue: impl Fn(usize) -> bool) -> Pause {
+        if let Some(ip) = self.instruction_pointer() {
+            if self.breakpoints.contains(ip) && self.paused_at.as_ref() != Some(ip) {
+                return Pause::Breakpoint(ip.clone());
+            }
+        }
+        loop {
+            match self.machine.step() {
+                StepStatus::Continue => {}
+                StepStatus::Halted => return Pause::Halted,
+                StepStatus::Errored(exception) => return Pause::Errored(exception),
+            }
+            if let Some(ip) = self.instruction_pointer() {
+                if self.breakpoints.contains(ip) {
+                    return Pause::Breakpoint(ip.clone());
+                }
+            }
+            if !should_continue(self.call_depth()) {
+                return Pause::Stepped;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::code::Code;
+    use crate::runtime::debugger::{Debugger, Pause};
+    use crate::runtime::instruction_pointer::InstructionPointer;
+    use crate::test_support::{self, Constant};
+
+    fn code() -> Code<'static, Constant> {
+        test_support::code(vec![
+            // PUSH 1; CALL #1; PUSH 3
+            vec![0, 1, 2, 1, 0, 3],
+            // PUSH 2; RET
+            vec![0, 2, 4],
+        ])
+    }
+
+    #[test]
+    fn resume_should_pause_at_breakpoint() {
+        let code = code();
+        let mut machine = test_support::machine(&code);
+        let mut debugger = Debugger::new(&mut machine);
+        debugger.add_breakpoint(1, 2);
+        assert!(matches!(debugger.resume(), Pause::Breakpoint(_)));
+        assert_eq!(&[1, 2], debugger.operands());
+        assert_eq!(2, debugger.call_depth());
+        assert!(matches!(debugger.resume(), Pause::Halted));
+        assert_eq!(&[1, 2, 3], debugger.operands());
+    }
+
+    #[test]
+    fn resume_should_pause_at_breakpoint_at_entry() {
+        let code = code();
+        let mut machine = test_support::machine(&code);
+        let mut debugger = Debugger::new(&mut machine);
+        debugger.add_breakpoint(0, 0);
+        assert!(matches!(debugger.resume(), Pause::Breakpoint(_)));
+        assert!(debugger.operands().is_empty());
+        assert!(matches!(debugger.resume(), Pause::Halted));
+        assert_eq!(&[1, 2, 3], debugger.operands());
+    }
+
+    #[test]
+    fn step_into_should_enter_called_function() {
+        let code = code();
+        let mut machine = test_support::machine(&code);
+        let mut debugger = Debugger::new(&mut machine);
+        debugger.step_into();
+        assert!(matches!(debugger.step_into(), Pause::Stepped));
+        assert_eq!(2, debugger.call_depth());
+        assert_eq!(
+            &InstructionPointer::new(1),
+            debugger.instruction_pointer().unwrap()
+        );
+    }
+
+    #[test]
+    fn step_over_should_run_called_function() {
+        let code = code();
+        let mut machine = test_support::machine(&code);
+        let mut debugger = Debugger::new(&mut machine);
+        debugger.step_over();
+        assert!(matches!(debugger.step_over(), Pause::Stepped));
+        assert_eq!(1, debugger.call_depth());
+        assert_eq!(&[1, 2], debugger.operands());
+    }
+
+    #[test]
+    fn step_out_should_return_from_current_function() {
+        let code = code();
+        let mut machine = test_support::machine(&code);
+        let mut debugger = Debugger::new(&mut machine);
+        debugger.step_into();
+        debugger.step_into();
+        assert!(matches!(debugger.step_out(), Pause::Stepped));
+        assert_eq!(1, debugger.call_depth());
+        assert_eq!(
+            4,
+            debugger.instruction_pointer().unwrap().instruction_pointer
+        );
+    }
+}
@@ -0,0 +1,38 @@
+/// A region of a chunk in which runtime exceptions are caught.
+///
+/// The region contains bytes from `start` (inclusive) to `end` (exclusive) and should
+/// cover whole instructions. When an exception is raised inside the region, the operand stack
+/// is truncated to `start_slot` operands above the start of the current `CallFrame`,
+/// the exception is pushed onto the stack and the execution continues from the `handler` offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExceptionHandler {
+    pub start: usize,
+    pub end: usize,
+    pub handler: usize,
+    pub start_slot: usize,
+}
+
+impl ExceptionHandler {
+    pub fn covers(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::exception_handler::ExceptionHandler;
+
+    #[test]
+    fn handler_should_cover_offsets_from_start_to_end_exclusive() {
+        let handler = ExceptionHandler {
+            start: 2,
+            end: 5,
+            handler: 10,
+            start_slot: 0,
+        };
+        assert!(!handler.covers(1));
+        assert!(handler.covers(2));
+        assert!(handler.covers(4));
+        assert!(!handler.covers(5));
+    }
+}
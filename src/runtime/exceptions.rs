@@ -92,3 +92,74 @@ impl From<ConstantNotFound> for Exception {
         }
     }
 }
+
+/// Raised when the `Machine` does not have enough fuel to run the next instruction.
+///
+/// The instruction is not run, so the execution can be resumed after the machine is refuelled.
+#[derive(Debug)]
+pub struct OutOfFuel {
+    pub required: u64,
+    pub remaining: u64,
+}
+
+impl From<OutOfFuel> for Exception {
+    fn from(exception: OutOfFuel) -> Self {
+        Exception {
+            exception_type: ExceptionType::Runtime,
+            name: "OutOfFuel".to_string(),
+            message: format!(
+                "Instruction requires {} fuel but only {} is left",
+                exception.required, exception.remaining
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StackOverflow {
+    pub max_depth: usize,
+}
+
+impl From<StackOverflow> for Exception {
+    fn from(exception: StackOverflow) -> Self {
+        Exception {
+            exception_type: ExceptionType::Runtime,
+            name: "StackOverflow".to_string(),
+            message: format!(
+                "Operand stack exceeded the maximum depth of {}",
+                exception.max_depth
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CallStackOverflow {
+    pub max_depth: usize,
+}
+
+impl From<CallStackOverflow> for Exception {
+    fn from(exception: CallStackOverflow) -> Self {
+        Exception {
+            exception_type: ExceptionType::Runtime,
+            name: "CallStackOverflow".to_string(),
+            message: format!(
+                "Call stack exceeded the maximum depth of {}",
+                exception.max_depth
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NativeNotFound(pub usize);
+
+impl From<NativeNotFound> for Exception {
+    fn from(exception: NativeNotFound) -> Self {
+        Exception {
+            exception_type: ExceptionType::Runtime,
+            name: "NativeNotFound".to_string(),
+            message: format!("No native function with id {} found", exception.0),
+        }
+    }
+}
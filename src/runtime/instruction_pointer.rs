@@ -5,7 +5,7 @@ use crate::Chunk;
 /// `chunk_id` is the id of the chunk where the code is located
 /// and `instruction_pointer` is the index of the next byte which is to be read.
 ///
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct InstructionPointer {
     pub chunk_id: usize,
     pub instruction_pointer: usize,
@@ -55,7 +55,8 @@ mod tests {
 
         let chunk = Chunk {
             constants: vec![0],
-            code: expected_code.clone(),
+            code: expected_code.clone().into(),
+            exception_handlers: vec![],
         };
 
         let mut pointer = InstructionPointer::new(0);
@@ -73,7 +74,8 @@ mod tests {
 
         let chunk = Chunk {
             constants: vec![0],
-            code,
+            code: code.into(),
+            exception_handlers: vec![],
         };
 
         let expected_code: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 2, 3, 4, 5];
@@ -96,7 +98,8 @@ mod tests {
     fn should_jump_forward() {
         let chunk = Chunk {
             constants: vec![0],
-            code: vec![0, 1, 2, 3, 4, 5],
+            code: vec![0, 1, 2, 3, 4, 5].into(),
+            exception_handlers: vec![],
         };
 
         let mut pointer = InstructionPointer::new(0);
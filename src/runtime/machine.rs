@@ -1,33 +1,68 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::mem;
 
-use crate::byte_readable::ByteReadable;
+use crate::byte_readable::{ByteOrder, ByteReadable};
 use crate::code::Code;
-use crate::exception::Exception;
+use crate::exception::{Exception, ExceptionType};
 use crate::instruction::Instruction;
 use crate::instruction_table::InstructionTable;
 use crate::runtime::call_frame::CallFrame;
+use crate::runtime::exception_handler::ExceptionHandler;
 use crate::runtime::exceptions::{
-    EmptyCallStack, EmptyOperandStack, SlotOutOfBounds, UnknownOpCode,
+    CallStackOverflow, ChunkNotFound, EmptyCallStack, EmptyOperandStack, NativeNotFound, OutOfFuel,
+    SlotOutOfBounds, StackOverflow, UnexpectedEndOfCode, UnknownOpCode,
 };
 use crate::runtime::instruction_pointer::InstructionPointer;
+use crate::runtime::native::NativeRegistry;
+use crate::runtime::observer::ExecutionObserver;
+use crate::runtime::outcome::{Outcome, StackTraceEntry, VmError};
 use crate::runtime::stack::Stack;
 use log::debug;
 
+/// The result of running a single instruction with `Machine::step`
+#[derive(Debug)]
+pub enum StepStatus {
+    /// The instruction was run and the VM can continue
+    Continue,
+    /// There is no code left to execute
+    Halted,
+    /// The instruction raised an exception
+    Errored(Exception),
+}
+
 /// The entire state of the VM
 ///
 /// State contains the `code` that the VM is executing and a hashmap of all global variables.
+/// If `fuel` is set, the cost of each instruction is subtracted from it
+/// and the execution stops with `OutOfFuel` when there is not enough fuel left.
+/// The depths of the operand stack and the call stack can be limited,
+/// exceeding them raises `StackOverflow` and `CallStackOverflow` respectively.
+///
+/// Runtime exceptions raised by instructions can be caught by the `ExceptionHandler`s
+/// of the chunks and by handlers that the host registers with `add_exception_handler`.
 pub struct Machine<'a, Constant, Value: Debug> {
-    pub code: &'a Code<Constant>,
+    pub code: &'a Code<'a, Constant>,
     instruction_table: InstructionTable<'a, Constant, Value>,
     operands: Stack<Value>,
     frames: Stack<CallFrame>,
     pub globals: HashMap<String, Value>,
+    fuel: Option<u64>,
+    max_operand_stack_depth: Option<usize>,
+    max_call_depth: Option<usize>,
+    natives: NativeRegistry<'a, Constant, Value>,
+    failed_native: Option<String>,
+    exception_handlers: HashMap<usize, Vec<ExceptionHandler>>,
+    exception_to_value: Option<fn(Exception) -> Value>,
+    /// Frames below this depth belong to the host and cannot catch exceptions
+    frame_floor: usize,
+    observers: Vec<Box<dyn ExecutionObserver<Constant, Value> + 'a>>,
+    byte_order: ByteOrder,
 }
 
 impl<'a, Constant, Value: Debug> Machine<'a, Constant, Value> {
     pub fn new(
-        code: &'a Code<Constant>,
+        code: &'a Code<'a, Constant>,
         instruction_table: InstructionTable<'a, Constant, Value>,
     ) -> Machine<'a, Constant, Value> {
         Machine {
@@ -36,33 +71,411 @@ impl<'a, Constant, Value: Debug> Machine<'a, Constant, Value> {
             operands: Stack::empty(),
             frames: Stack::empty(),
             globals: HashMap::new(),
+            fuel: None,
+            max_operand_stack_depth: None,
+            max_call_depth: None,
+            natives: NativeRegistry::new(),
+            failed_native: None,
+            exception_handlers: HashMap::new(),
+            exception_to_value: None,
+            frame_floor: 0,
+            observers: vec![],
+            byte_order: ByteOrder::LittleEndian,
         }
     }
 
-    pub fn start(&mut self) -> bool {
-        let result = self.run();
-        if let Err(exception) = result {
-            self.raise_exception(exception);
-            false
-        } else {
-            true
+    /// Runs the code until it ends or an exception is raised.
+    ///
+    /// On success returns the operand that was left on top of the stack.
+    pub fn start(&mut self) -> Result<Outcome<Value>, VmError> {
+        self.failed_native = None;
+        match self.run() {
+            Ok(()) => Ok(Outcome {
+                value: self.operands.pop(),
+            }),
+            Err(exception) => Err(VmError {
+                exception,
+                stack_trace: self.stack_trace(),
+            }),
+        }
+    }
+
+    /// Calls the chunk with the given arguments and runs it until it returns.
+    ///
+    /// The arguments are pushed onto the operand stack and a new `CallFrame` is created
+    /// that starts at the first argument. The chunk is expected to discard its frame and
+    /// push the returned value, which is then popped and returned.
+    /// The rest of the machine state is left intact, even if an exception was raised.
+    pub fn call_chunk(&mut self, chunk_id: usize, args: Vec<Value>) -> Result<Value, VmError> {
+        self.failed_native = None;
+        let depth = self.frames.len();
+        let start_slot = self.operands.len();
+        if self.code.get_chunk(chunk_id).is_none() {
+            return Err(VmError {
+                exception: Exception::from(ChunkNotFound(chunk_id)),
+                stack_trace: self.stack_trace(),
+            });
+        }
+        for arg in args {
+            self.push_operand(arg);
+        }
+        self.push_frame(chunk_id, "<host call>".to_string(), start_slot);
+        let frame_floor = self.frame_floor;
+        self.frame_floor = depth;
+        let result = self
+            .check_stack_limits()
+            .and_then(|_| self.run_until_returned(depth))
+            .and_then(|_| self.pop_returned_value(start_slot));
+        self.frame_floor = frame_floor;
+        match result {
+            Ok(value) => Ok(value),
+            Err(exception) => {
+                let error = VmError {
+                    exception,
+                    stack_trace: self.stack_trace(),
+                };
+                self.unwind(depth, start_slot);
+                Err(error)
+            }
+        }
+    }
+
+    /// Runs the code until the call stack has only `depth` frames left.
+    fn run_until_returned(&mut self, depth: usize) -> Result<(), Exception> {
+        while self.frames.len() > depth {
+            if !self.run_instruction()? {
+                let chunk_id = self.peek_frame()?.instruction_pointer.chunk_id;
+                return Err(Exception::from(UnexpectedEndOfCode { chunk_id }));
+            }
+        }
+        Ok(())
+    }
+
+    fn pop_returned_value(&mut self, start_slot: usize) -> Result<Value, Exception> {
+        if self.operands.len() <= start_slot {
+            return Err(Exception::from(EmptyOperandStack));
         }
+        let value = self.pop_operand()?;
+        self.unwind(self.frames.len(), start_slot);
+        Ok(value)
+    }
+
+    /// Discards all frames above `depth` and all operands above `start_slot`.
+    fn unwind(&mut self, depth: usize, start_slot: usize) {
+        while self.frames.len() > depth {
+            let _ = self.discard_frame();
+        }
+        while self.operands.len() > start_slot {
+            self.operands.pop();
+        }
+    }
+
+    /// Decodes and runs the next instruction.
+    pub fn step(&mut self) -> StepStatus {
+        match self.run_instruction() {
+            Ok(true) => StepStatus::Continue,
+            Ok(false) => StepStatus::Halted,
+            Err(exception) => StepStatus::Errored(exception),
+        }
+    }
+
+    /// Runs at most `n_steps` instructions.
+    ///
+    /// Returns `Continue` if all `n_steps` instructions were run and the VM can continue.
+    pub fn run_for(&mut self, n_steps: usize) -> StepStatus {
+        for _ in 0..n_steps {
+            match self.step() {
+                StepStatus::Continue => {}
+                status => return status,
+            }
+        }
+        StepStatus::Continue
     }
 
     fn run(&mut self) -> Result<(), Exception> {
-        while let Some(op_code) = self.next_byte() {
-            let instruction = self.find_instruction(op_code)?;
-            let arguments_ip = self.instruction_pointer()?.clone();
-            self.instruction_pointer()?
-                .jump_forward(instruction.instruction_fn.byte_arity());
-            debug!("Running instruction {}.", instruction.name);
-            debug!("\tStack before: {:?}", self.operands);
-            instruction.instruction_fn.run(self, arguments_ip)?;
-            debug!("\tStack after: {:?}", self.operands);
+        while self.run_instruction()? {}
+        Ok(())
+    }
+
+    /// Runs the next instruction and returns `false` if there was no code left to execute.
+    fn run_instruction(&mut self) -> Result<bool, Exception> {
+        self.failed_native = None;
+        let op_code = match self.next_byte() {
+            Some(op_code) => op_code,
+            None => return Ok(false),
+        };
+        let instruction = match self.find_instruction(op_code) {
+            Ok(instruction) => instruction,
+            Err(exception) => return Err(self.raise(Exception::from(exception))),
+        };
+        if let Err(exception) = self.consume_fuel(op_code) {
+            return Err(self.raise(exception));
+        }
+        let depth = self.frames.len();
+        let arguments_ip = self.instruction_pointer()?.clone();
+        let instruction_ip = InstructionPointer {
+            chunk_id: arguments_ip.chunk_id,
+            instruction_pointer: arguments_ip.instruction_pointer - 1,
+        };
+        self.instruction_pointer()?
+            .jump_forward(instruction.instruction_fn.byte_arity());
+        self.notify(|observer, machine| {
+            observer.before_instruction(machine, &instruction_ip, instruction)
+        });
+        debug!("Running instruction {}.", instruction.name);
+        debug!("\tStack before: {:?}", self.operands);
+        let result = instruction
+            .instruction_fn
+            .run(self, arguments_ip)
+            .and_then(|_| self.check_stack_limits());
+        let result = match result {
+            Ok(()) => Ok(()),
+            Err(exception) => {
+                let exception = self.raise(exception);
+                while self.frames.len() > depth {
+                    self.discard_frame()?;
+                }
+                self.handle_exception(exception, &instruction_ip, depth)
+            }
+        };
+        debug!("\tStack after: {:?}", self.operands);
+        self.notify(|observer, machine| {
+            observer.after_instruction(machine, &instruction_ip, instruction)
+        });
+        result?;
+        Ok(true)
+    }
+
+    /// Registers an observer that receives events from the dispatch loop.
+    pub fn add_observer(&mut self, observer: impl ExecutionObserver<Constant, Value> + 'a) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Calls `event` for each observer.
+    fn notify(
+        &mut self,
+        mut event: impl FnMut(&mut (dyn ExecutionObserver<Constant, Value> + 'a), &Self),
+    ) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let mut observers = mem::take(&mut self.observers);
+        for observer in observers.iter_mut() {
+            event(observer.as_mut(), self);
+        }
+        self.observers = observers;
+    }
+
+    /// Notifies observers about the exception and returns it back.
+    fn raise(&mut self, exception: Exception) -> Exception {
+        self.notify(|observer, machine| observer.exception_raised(machine, &exception));
+        exception
+    }
+
+    /// Registers a handler for exceptions that are raised inside a region of the chunk
+    /// in addition to the `exception_handlers` of the chunk.
+    ///
+    /// If several handlers cover the same instruction, the handlers of the chunk are used first,
+    /// then the registered ones in the order they were added.
+    pub fn add_exception_handler(&mut self, chunk_id: usize, handler: ExceptionHandler) {
+        self.exception_handlers
+            .entry(chunk_id)
+            .or_default()
+            .push(handler);
+    }
+
+    /// Sets a function that converts caught exceptions to values that are passed to handlers.
+    ///
+    /// If it is not set, nothing is pushed onto the operand stack when an exception is caught.
+    pub fn set_exception_to_value(&mut self, exception_to_value: fn(Exception) -> Value) {
+        self.exception_to_value = Some(exception_to_value);
+    }
+
+    /// Finds a handler for a runtime exception that was raised by the instruction at `failed_at`
+    /// and unwinds the call stack and the operand stack down to it.
+    ///
+    /// The instruction was run by the frame at `depth - 1`, frames that it pushed
+    /// must be discarded before.
+    /// Returns the exception back if it was not caught, the state of the machine is not changed.
+    fn handle_exception(
+        &mut self,
+        exception: Exception,
+        failed_at: &InstructionPointer,
+        depth: usize,
+    ) -> Result<(), Exception> {
+        if let ExceptionType::Static = exception.exception_type {
+            return Err(exception);
+        }
+        let (frame_index, handler) = match self.find_exception_handler(failed_at, depth) {
+            Some(found) => found,
+            None => return Err(exception),
+        };
+        while self.frames.len() > frame_index + 1 {
+            self.discard_frame()?;
+        }
+        let frame = self.peek_frame()?;
+        let start_slot = frame.start_slot + handler.start_slot;
+        while self.operands.len() > start_slot {
+            self.operands.pop();
+        }
+        if let Some(exception_to_value) = self.exception_to_value {
+            self.push_operand(exception_to_value(exception));
+        }
+        self.instruction_pointer()?.instruction_pointer = handler.handler;
+        Ok(())
+    }
+
+    /// Returns the index of the innermost frame that can catch the exception and its handler.
+    ///
+    /// The frame that ran the failed instruction is checked at its offset,
+    /// the rest of the frames are checked at the instruction that called the next frame.
+    /// If the instruction discarded its own frame, the search starts from the caller.
+    fn find_exception_handler(
+        &self,
+        failed_at: &InstructionPointer,
+        depth: usize,
+    ) -> Option<(usize, ExceptionHandler)> {
+        let frames = self.frames.as_slice();
+        for frame_index in (self.frame_floor..frames.len().min(depth)).rev() {
+            let ip = &frames[frame_index].instruction_pointer;
+            let location = if frame_index + 1 == depth && ip.chunk_id == failed_at.chunk_id {
+                failed_at.instruction_pointer
+            } else {
+                ip.instruction_pointer.saturating_sub(1)
+            };
+            let chunk_handlers = self
+                .code
+                .get_chunk(ip.chunk_id)
+                .map(|chunk| chunk.exception_handlers.as_slice())
+                .unwrap_or_default();
+            let registered_handlers = self
+                .exception_handlers
+                .get(&ip.chunk_id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let handler = chunk_handlers
+                .iter()
+                .chain(registered_handlers)
+                .find(|handler| handler.covers(location));
+            if let Some(handler) = handler {
+                return Some((frame_index, handler.clone()));
+            }
+        }
+        None
+    }
+
+    /// Returns the remaining fuel or `None` if the fuel is unlimited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Sets the fuel budget, `None` means that the fuel is unlimited.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// Adds `amount` to the fuel budget. Does nothing if the fuel is unlimited.
+    pub fn refuel(&mut self, amount: u64) {
+        if let Some(fuel) = self.fuel {
+            self.fuel = Some(fuel.saturating_add(amount));
+        }
+    }
+
+    /// Subtracts the cost of the instruction from the fuel.
+    ///
+    /// If there is not enough fuel, the instruction pointer is moved back to `op_code`
+    /// so that the instruction is run again after the machine is refuelled.
+    fn consume_fuel(&mut self, op_code: u8) -> Result<(), Exception> {
+        if let Some(fuel) = self.fuel {
+            let cost = self.instruction_table.get_cost(op_code);
+            if cost > fuel {
+                self.instruction_pointer()?.jump_backward(1);
+                return Err(Exception::from(OutOfFuel {
+                    required: cost,
+                    remaining: fuel,
+                }));
+            }
+            self.fuel = Some(fuel - cost);
         }
         Ok(())
     }
 
+    /// Limits the depth of the operand stack, `None` means that the depth is unlimited.
+    pub fn set_max_operand_stack_depth(&mut self, max_depth: Option<usize>) {
+        self.max_operand_stack_depth = max_depth;
+    }
+
+    /// Limits the depth of the call stack, `None` means that the depth is unlimited.
+    pub fn set_max_call_depth(&mut self, max_depth: Option<usize>) {
+        self.max_call_depth = max_depth;
+    }
+
+    /// Operands above the limit are discarded when the operand stack overflows.
+    /// Frames above the limit are discarded by the caller that knows which frames were pushed.
+    fn check_stack_limits(&mut self) -> Result<(), Exception> {
+        if let Some(max_depth) = self.max_operand_stack_depth {
+            if self.operands.len() > max_depth {
+                while self.operands.len() > max_depth {
+                    self.operands.pop();
+                }
+                return Err(Exception::from(StackOverflow { max_depth }));
+            }
+        }
+        if let Some(max_depth) = self.max_call_depth {
+            if self.frames.len() > max_depth {
+                return Err(Exception::from(CallStackOverflow { max_depth }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers a host function that can be called from bytecode and returns its id.
+    pub fn register_native<F>(&mut self, name: &str, arity: usize, function: F) -> usize
+    where
+        F: Fn(&mut Machine<'a, Constant, Value>, Vec<Value>) -> Result<Value, Exception> + 'a,
+    {
+        self.natives.register(name, arity, function)
+    }
+
+    pub fn natives(&self) -> &NativeRegistry<'a, Constant, Value> {
+        &self.natives
+    }
+
+    /// Calls the host function with the given id.
+    ///
+    /// Pops `arity` arguments from the operand stack and pushes the returned value.
+    pub fn call_native(&mut self, native_id: usize) -> Result<(), Exception> {
+        let native = self
+            .natives
+            .get(native_id)
+            .cloned()
+            .ok_or(NativeNotFound(native_id))?;
+        let mut arguments = Vec::with_capacity(native.arity);
+        for _ in 0..native.arity {
+            arguments.push(self.pop_operand()?);
+        }
+        arguments.reverse();
+        match (native.function)(self, arguments) {
+            Ok(result) => {
+                self.push_operand(result);
+                Ok(())
+            }
+            Err(exception) => {
+                self.failed_native = Some(native.name);
+                Err(exception)
+            }
+        }
+    }
+
+    /// Sets the byte order of multi-byte instruction arguments read through the machine.
+    pub fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.byte_order = byte_order;
+    }
+
+    pub fn instruction_table(&self) -> &InstructionTable<'a, Constant, Value> {
+        &self.instruction_table
+    }
+
     pub fn push_operand(&mut self, operand: Value) {
         self.operands.push(operand)
     }
@@ -99,6 +512,16 @@ impl<'a, Constant, Value: Debug> Machine<'a, Constant, Value> {
         self.operands.len()
     }
 
+    /// Returns the operand stack starting from the bottom.
+    pub fn operands(&self) -> &[Value] {
+        self.operands.as_slice()
+    }
+
+    /// Returns the call stack starting from the outermost frame.
+    pub fn frames(&self) -> &[CallFrame] {
+        self.frames.as_slice()
+    }
+
     pub fn peek_frame(&self) -> Result<&CallFrame, EmptyCallStack> {
         self.frames.peek().ok_or(EmptyCallStack)
     }
@@ -106,6 +529,11 @@ impl<'a, Constant, Value: Debug> Machine<'a, Constant, Value> {
     pub fn push_frame(&mut self, chunk_id: usize, name: String, start_slot: usize) {
         let frame = CallFrame::new(chunk_id, name, start_slot);
         self.frames.push(frame);
+        self.notify(|observer, machine| {
+            if let Ok(frame) = machine.peek_frame() {
+                observer.frame_pushed(machine, frame)
+            }
+        });
     }
 
     pub fn discard_frame(&mut self) -> Result<CallFrame, EmptyCallStack> {
@@ -115,9 +543,26 @@ impl<'a, Constant, Value: Debug> Machine<'a, Constant, Value> {
         while self.operands.len() > last_frame_start {
             self.operands.pop();
         }
+        self.notify(|observer, machine| observer.frame_discarded(machine, &last_frame));
         Ok(last_frame)
     }
 
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Writes a global and notifies observers.
+    ///
+    /// Writing to `globals` directly does not notify observers.
+    pub fn set_global(&mut self, name: String, value: Value) {
+        self.globals.insert(name.clone(), value);
+        self.notify(|observer, machine| {
+            if let Some(value) = machine.globals.get(&name) {
+                observer.global_written(machine, &name, value)
+            }
+        });
+    }
+
     fn next_byte(&mut self) -> Option<u8> {
         let code = self.code;
         let ip = self.instruction_pointer().ok()?;
@@ -142,11 +587,17 @@ impl<'a, Constant, Value: Debug> Machine<'a, Constant, Value> {
         }
     }
 
-    fn raise_exception(&self, exception: Exception) {
-        println!("{}", exception);
-        for frame in self.frames.rev() {
-            println!("\tat {}", frame);
-        }
+    /// Returns the active call frames starting from the innermost one.
+    ///
+    /// If the last exception was raised by a host function, it is the first entry.
+    pub fn stack_trace(&self) -> Vec<StackTraceEntry> {
+        let native = self
+            .failed_native
+            .iter()
+            .map(|name| StackTraceEntry::Native { name: name.clone() });
+        native
+            .chain(self.frames.rev().map(StackTraceEntry::from))
+            .collect()
     }
 }
 
@@ -158,4 +609,452 @@ impl<'a, Constant, Value: Debug> ByteReadable<InstructionPointer> for Machine<'a
     fn has_next(&self, ptr: &InstructionPointer) -> bool {
         self.code.has_next(ptr)
     }
+
+    fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exception::{Exception, ExceptionType};
+    use crate::instruction::Instruction;
+    use crate::instruction_table::InstructionTable;
+    use crate::runtime::call_frame::CallFrame;
+    use crate::runtime::exception_handler::ExceptionHandler;
+    use crate::runtime::instruction_pointer::InstructionPointer;
+    use crate::runtime::machine::{Machine, StepStatus};
+    use crate::runtime::observer::ExecutionObserver;
+    use crate::runtime::outcome::StackTraceEntry;
+    use crate::test_support::{code, machine, Constant, Value, INSTRUCTIONS};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn step_should_run_one_instruction() {
+        let code = code(vec![vec![0, 2, 0, 3, 1]]);
+        let mut machine = machine(&code);
+        assert!(matches!(machine.step(), StepStatus::Continue));
+        assert_eq!(1, machine.operand_stack_len());
+        assert_eq!(2, *machine.peek_operand().unwrap());
+    }
+
+    #[test]
+    fn step_should_halt_when_code_ends() {
+        let code = code(vec![vec![0, 2]]);
+        let mut machine = machine(&code);
+        assert!(matches!(machine.step(), StepStatus::Continue));
+        assert!(matches!(machine.step(), StepStatus::Halted));
+    }
+
+    #[test]
+    fn step_should_return_exception_on_unknown_opcode() {
+        let code = code(vec![vec![42]]);
+        let mut machine = machine(&code);
+        if let StepStatus::Errored(exception) = machine.step() {
+            assert_eq!("UnknownOpCode", exception.name);
+        } else {
+            panic!("Expected an exception");
+        }
+    }
+
+    #[test]
+    fn run_for_should_stop_after_n_steps() {
+        let code = code(vec![vec![0, 2, 0, 3, 1]]);
+        let mut machine = machine(&code);
+        assert!(matches!(machine.run_for(2), StepStatus::Continue));
+        assert_eq!(2, machine.operand_stack_len());
+        assert!(matches!(machine.run_for(10), StepStatus::Halted));
+        assert_eq!(5, *machine.peek_operand().unwrap());
+    }
+
+    #[test]
+    fn start_should_return_value_on_top_of_the_stack() {
+        let code = code(vec![vec![0, 2, 0, 3, 1]]);
+        let mut machine = machine(&code);
+        let outcome = machine.start().unwrap();
+        assert_eq!(Some(5), outcome.value);
+    }
+
+    #[test]
+    fn start_should_return_exception_with_stack_trace() {
+        let code = code(vec![vec![0, 2, 42]]);
+        let mut machine = machine(&code);
+        let error = machine.start().unwrap_err();
+        assert_eq!("UnknownOpCode", error.exception.name);
+        assert_eq!(
+            vec![StackTraceEntry::Frame {
+                chunk_id: 0,
+                name: "main".to_string(),
+                instruction_pointer: 3,
+            }],
+            error.stack_trace
+        );
+    }
+
+    #[test]
+    fn machine_should_run_out_of_fuel() {
+        let code = code(vec![vec![0, 2, 0, 3, 1]]);
+        let mut machine = machine(&code);
+        machine.set_fuel(Some(2));
+        let error = machine.start().unwrap_err();
+        assert_eq!("OutOfFuel", error.exception.name);
+        assert_eq!(Some(0), machine.fuel());
+        assert_eq!(2, machine.operand_stack_len());
+    }
+
+    #[test]
+    fn machine_should_resume_after_refuel() {
+        let code = code(vec![vec![0, 2, 0, 3, 1]]);
+        let mut machine = machine(&code);
+        machine.set_fuel(Some(2));
+        assert!(machine.start().is_err());
+        machine.refuel(1);
+        assert_eq!(Some(5), machine.start().unwrap().value);
+    }
+
+    #[test]
+    fn instruction_cost_should_be_subtracted_from_fuel() {
+        let code = code(vec![vec![0, 2, 0, 3, 1]]);
+        let mut table = InstructionTable::instructions(&INSTRUCTIONS);
+        table.set_cost(1, 10);
+        let mut machine = Machine::new(&code, table);
+        machine.push_frame(0, "main".to_string(), 0);
+        machine.set_fuel(Some(11));
+        assert!(matches!(machine.run_for(2), StepStatus::Continue));
+        assert!(matches!(machine.step(), StepStatus::Errored(_)));
+        machine.refuel(1);
+        assert!(matches!(machine.step(), StepStatus::Continue));
+        assert_eq!(Some(0), machine.fuel());
+    }
+
+    #[test]
+    fn exceeding_max_operand_stack_depth_should_raise_stack_overflow() {
+        let code = code(vec![vec![0, 1, 0, 2, 0, 3]]);
+        let mut machine = machine(&code);
+        machine.set_max_operand_stack_depth(Some(2));
+        let error = machine.start().unwrap_err();
+        assert_eq!("StackOverflow", error.exception.name);
+        assert_eq!(1, error.stack_trace.len());
+        assert_eq!(&[1, 2], machine.operands());
+    }
+
+    #[test]
+    fn exceeding_max_call_depth_should_raise_call_stack_overflow() {
+        // CALL #0
+        let code = code(vec![vec![2, 0]]);
+        let mut machine = machine(&code);
+        machine.set_max_call_depth(Some(100));
+        let error = machine.start().unwrap_err();
+        assert_eq!("CallStackOverflow", error.exception.name);
+        assert_eq!(100, error.stack_trace.len());
+        assert_eq!(100, machine.frames().len());
+    }
+
+    #[test]
+    fn native_function_should_receive_arguments_in_order() {
+        let code = code(vec![vec![0, 5, 0, 3, 3, 0]]);
+        let mut machine = machine(&code);
+        machine.register_native("sub", 2, |_, args| Ok(args[0] - args[1]));
+        assert_eq!(Some(2), machine.start().unwrap().value);
+    }
+
+    #[test]
+    fn native_function_exception_should_include_native_name_in_stack_trace() {
+        let code = code(vec![vec![3, 0]]);
+        let mut machine = machine(&code);
+        machine.register_native("fail", 0, |_, _| {
+            Err(Exception {
+                exception_type: ExceptionType::Runtime,
+                name: "Failure".to_string(),
+                message: "".to_string(),
+            })
+        });
+        let error = machine.start().unwrap_err();
+        assert_eq!("Failure", error.exception.name);
+        assert_eq!(
+            StackTraceEntry::Native {
+                name: "fail".to_string()
+            },
+            error.stack_trace[0]
+        );
+        assert_eq!(2, error.stack_trace.len());
+    }
+
+    #[test]
+    fn calling_unknown_native_function_should_raise_exception() {
+        let code = code(vec![vec![3, 7]]);
+        let mut machine = machine(&code);
+        let error = machine.start().unwrap_err();
+        assert_eq!("NativeNotFound", error.exception.name);
+    }
+
+    #[test]
+    fn call_chunk_should_return_value_and_keep_machine_state() {
+        let code = code(vec![vec![0, 7], vec![1, 4]]);
+        let mut machine = machine(&code);
+        machine.step();
+        assert_eq!(5, machine.call_chunk(1, vec![2, 3]).unwrap());
+        assert_eq!(&[7], machine.operands());
+        assert_eq!(1, machine.frames().len());
+        assert!(matches!(machine.step(), StepStatus::Halted));
+    }
+
+    #[test]
+    fn call_chunk_should_restore_machine_state_on_exception() {
+        let code = code(vec![vec![0, 7], vec![0, 1, 42]]);
+        let mut machine = machine(&code);
+        machine.step();
+        let error = machine.call_chunk(1, vec![2]).unwrap_err();
+        assert_eq!("UnknownOpCode", error.exception.name);
+        assert_eq!(2, error.stack_trace.len());
+        assert_eq!(&[7], machine.operands());
+        assert_eq!(1, machine.frames().len());
+    }
+
+    #[test]
+    fn call_chunk_should_raise_exception_if_chunk_does_not_return() {
+        let code = code(vec![vec![], vec![0, 1]]);
+        let mut machine = machine(&code);
+        let error = machine.call_chunk(1, vec![]).unwrap_err();
+        assert_eq!("UnexpectedEndOfCode", error.exception.name);
+        assert!(machine.operands().is_empty());
+    }
+
+    #[test]
+    fn call_chunk_should_raise_exception_if_chunk_does_not_exist() {
+        let code = code(vec![vec![]]);
+        let mut machine = machine(&code);
+        let error = machine.call_chunk(5, vec![1]).unwrap_err();
+        assert_eq!("ChunkNotFound", error.exception.name);
+        assert!(machine.operands().is_empty());
+    }
+
+    #[test]
+    fn call_chunk_should_not_report_native_that_failed_before() {
+        let code = code(vec![vec![3, 0]]);
+        let mut machine = machine(&code);
+        machine.register_native("fail", 0, |_, _| {
+            Err(Exception {
+                exception_type: ExceptionType::Runtime,
+                name: "Failure".to_string(),
+                message: "".to_string(),
+            })
+        });
+        assert!(machine.start().is_err());
+        let error = machine.call_chunk(99, vec![]).unwrap_err();
+        assert_eq!("ChunkNotFound", error.exception.name);
+        assert!(error
+            .stack_trace
+            .iter()
+            .all(|entry| !matches!(entry, StackTraceEntry::Native { .. })));
+    }
+
+    #[test]
+    fn call_chunk_should_respect_stack_limits() {
+        let code = code(vec![vec![], vec![4]]);
+        let mut machine = machine(&code);
+        machine.set_max_call_depth(Some(1));
+        let error = machine.call_chunk(1, vec![1]).unwrap_err();
+        assert_eq!("CallStackOverflow", error.exception.name);
+        assert_eq!(1, machine.frames().len());
+        assert!(machine.operands().is_empty());
+        machine.set_max_call_depth(None);
+        machine.set_max_operand_stack_depth(Some(1));
+        let error = machine.call_chunk(1, vec![1, 2]).unwrap_err();
+        assert_eq!("StackOverflow", error.exception.name);
+        assert!(machine.operands().is_empty());
+    }
+
+    fn exception_to_value(_: Exception) -> Value {
+        -1
+    }
+
+    #[test]
+    fn exception_should_be_caught_by_handler_in_the_same_frame() {
+        // PUSH 1; PUSH 2; THROW; PUSH 9; PUSH 3
+        let code = code(vec![vec![0, 1, 0, 2, 5, 0, 9, 0, 3]]);
+        let mut machine = machine(&code);
+        machine.set_exception_to_value(exception_to_value);
+        machine.add_exception_handler(
+            0,
+            ExceptionHandler {
+                start: 4,
+                end: 7,
+                handler: 7,
+                start_slot: 1,
+            },
+        );
+        assert!(machine.start().is_ok());
+        assert_eq!(&[1, -1], machine.operands());
+    }
+
+    #[test]
+    fn exception_should_be_caught_by_handler_of_the_chunk() {
+        // PUSH 1; THROW; PUSH 9; PUSH 3
+        let mut code = code(vec![vec![0, 1, 5, 0, 9, 0, 3]]);
+        code.chunks[0].exception_handlers.push(ExceptionHandler {
+            start: 2,
+            end: 5,
+            handler: 5,
+            start_slot: 1,
+        });
+        let mut machine = machine(&code);
+        machine.set_exception_to_value(exception_to_value);
+        assert!(machine.start().is_ok());
+        assert_eq!(&[1, -1], machine.operands());
+    }
+
+    #[test]
+    fn exception_should_be_caught_only_by_handlers_of_the_failed_frame_and_its_callers() {
+        let mut code = code(vec![
+            // PUSH 1; CALL #1
+            vec![0, 1, 2, 1],
+            // PUSH 9; RET
+            vec![0, 9, 4],
+        ]);
+        code.chunks[1].exception_handlers.push(ExceptionHandler {
+            start: 2,
+            end: 3,
+            handler: 0,
+            start_slot: 0,
+        });
+        let mut machine = machine(&code);
+        machine.set_max_call_depth(Some(1));
+        let error = machine.start().unwrap_err();
+        assert_eq!("CallStackOverflow", error.exception.name);
+        assert_eq!(1, machine.frames().len());
+    }
+
+    #[test]
+    fn exception_should_unwind_call_frames_to_the_handler() {
+        let code = code(vec![
+            // PUSH 1; CALL #1; PUSH 5
+            vec![0, 1, 2, 1, 0, 5],
+            // PUSH 8; THROW
+            vec![0, 8, 5],
+        ]);
+        let mut machine = machine(&code);
+        machine.set_exception_to_value(exception_to_value);
+        machine.add_exception_handler(
+            0,
+            ExceptionHandler {
+                start: 2,
+                end: 4,
+                handler: 4,
+                start_slot: 0,
+            },
+        );
+        machine.run_for(100);
+        assert_eq!(1, machine.frames().len());
+        assert_eq!(&[-1, 5], machine.operands());
+    }
+
+    #[test]
+    fn uncaught_exception_should_not_unwind_call_frames() {
+        let code = code(vec![vec![2, 1], vec![0, 8, 5]]);
+        let mut machine = machine(&code);
+        machine.add_exception_handler(
+            1,
+            ExceptionHandler {
+                start: 0,
+                end: 2,
+                handler: 0,
+                start_slot: 0,
+            },
+        );
+        let error = machine.start().unwrap_err();
+        assert_eq!("Thrown", error.exception.name);
+        assert_eq!(2, error.stack_trace.len());
+        assert_eq!(&[8], machine.operands());
+    }
+
+    #[test]
+    fn exception_in_host_call_should_not_be_caught_by_host_frames() {
+        let code = code(vec![vec![0, 1], vec![5]]);
+        let mut machine = machine(&code);
+        machine.add_exception_handler(
+            0,
+            ExceptionHandler {
+                start: 0,
+                end: 2,
+                handler: 0,
+                start_slot: 0,
+            },
+        );
+        let error = machine.call_chunk(1, vec![]).unwrap_err();
+        assert_eq!("Thrown", error.exception.name);
+        assert_eq!(1, machine.frames().len());
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl ExecutionObserver<Constant, Value> for Recorder {
+        fn before_instruction(
+            &mut self,
+            _: &Machine<Constant, Value>,
+            ip: &InstructionPointer,
+            instruction: &Instruction<Constant, Value>,
+        ) {
+            self.events.push(format!(
+                "before {} #{}:{}",
+                instruction.name, ip.chunk_id, ip.instruction_pointer
+            ));
+        }
+
+        fn after_instruction(
+            &mut self,
+            _: &Machine<Constant, Value>,
+            _: &InstructionPointer,
+            instruction: &Instruction<Constant, Value>,
+        ) {
+            self.events.push(format!("after {}", instruction.name));
+        }
+
+        fn frame_pushed(&mut self, _: &Machine<Constant, Value>, frame: &CallFrame) {
+            self.events.push(format!("push {}", frame.name));
+        }
+
+        fn frame_discarded(&mut self, _: &Machine<Constant, Value>, frame: &CallFrame) {
+            self.events.push(format!("discard {}", frame.name));
+        }
+
+        fn exception_raised(&mut self, _: &Machine<Constant, Value>, exception: &Exception) {
+            self.events.push(format!("exception {}", exception.name));
+        }
+
+        fn global_written(&mut self, _: &Machine<Constant, Value>, name: &str, value: &Value) {
+            self.events.push(format!("global {}={}", name, value));
+        }
+    }
+
+    #[test]
+    fn observer_should_receive_dispatch_loop_events() {
+        let code = code(vec![vec![2, 1, 5], vec![0, 3, 4]]);
+        let mut machine = machine(&code);
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        machine.add_observer(Rc::clone(&recorder));
+        machine.set_global("x".to_string(), 1);
+        assert!(machine.start().is_err());
+        assert_eq!(
+            vec![
+                "global x=1",
+                "before CALL #0:0",
+                "push f",
+                "after CALL",
+                "before PUSH #1:0",
+                "after PUSH",
+                "before RET #1:2",
+                "discard f",
+                "after RET",
+                "before THROW #0:2",
+                "exception Thrown",
+                "after THROW",
+            ],
+            recorder.borrow().events
+        );
+    }
 }
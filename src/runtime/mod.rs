@@ -1,9 +1,19 @@
 pub use call_frame::CallFrame;
+pub use debugger::{Debugger, Pause};
+pub use exception_handler::ExceptionHandler;
 pub use instruction_pointer::InstructionPointer;
-pub use machine::Machine;
+pub use machine::{Machine, StepStatus};
+pub use native::{NativeFn, NativeFunction, NativeRegistry, MAX_NATIVE_FUNCTIONS};
+pub use observer::ExecutionObserver;
+pub use outcome::{Outcome, StackTraceEntry, VmError};
 
 mod call_frame;
+mod debugger;
+mod exception_handler;
 pub mod exceptions;
 mod instruction_pointer;
 mod machine;
+mod native;
+mod observer;
+mod outcome;
 mod stack;
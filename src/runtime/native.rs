@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::exception::Exception;
+use crate::runtime::machine::Machine;
+
+/// A host function that can be called from bytecode.
+///
+/// Receives the machine and the arguments popped from the operand stack
+/// (the first argument is the deepest one) and returns a value that is pushed onto the stack.
+pub type NativeFn<'a, Constant, Value> =
+    Rc<dyn Fn(&mut Machine<'a, Constant, Value>, Vec<Value>) -> Result<Value, Exception> + 'a>;
+
+/// A named host function that accepts `arity` arguments
+pub struct NativeFunction<'a, Constant, Value: Debug> {
+    pub name: String,
+    pub arity: usize,
+    pub function: NativeFn<'a, Constant, Value>,
+}
+
+impl<'a, Constant, Value: Debug> Clone for NativeFunction<'a, Constant, Value> {
+    fn clone(&self) -> Self {
+        NativeFunction {
+            name: self.name.clone(),
+            arity: self.arity,
+            function: Rc::clone(&self.function),
+        }
+    }
+}
+
+/// The maximum number of host functions, `CallNative` encodes the id in a single byte
+pub const MAX_NATIVE_FUNCTIONS: usize = 256;
+
+/// A set of host functions
+///
+/// Every registered function gets a unique id which is used to call it from bytecode.
+/// Ids are assigned sequentially starting from 0, at most `MAX_NATIVE_FUNCTIONS` can be registered.
+pub struct NativeRegistry<'a, Constant, Value: Debug> {
+    functions: Vec<NativeFunction<'a, Constant, Value>>,
+    ids: HashMap<String, usize>,
+}
+
+impl<'a, Constant, Value: Debug> NativeRegistry<'a, Constant, Value> {
+    pub fn new() -> NativeRegistry<'a, Constant, Value> {
+        NativeRegistry {
+            functions: vec![],
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Registers a function and returns its id.
+    ///
+    /// Panics if the name is already registered or the registry is full.
+    pub fn register<F>(&mut self, name: &str, arity: usize, function: F) -> usize
+    where
+        F: Fn(&mut Machine<'a, Constant, Value>, Vec<Value>) -> Result<Value, Exception> + 'a,
+    {
+        if self.ids.contains_key(name) {
+            panic!("Native function {} is already registered", name);
+        }
+        if self.functions.len() == MAX_NATIVE_FUNCTIONS {
+            panic!(
+                "Native function {} cannot be registered, at most {} are supported",
+                name, MAX_NATIVE_FUNCTIONS
+            );
+        }
+        let id = self.functions.len();
+        self.functions.push(NativeFunction {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
+        });
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+
+    pub fn get(&self, id: usize) -> Option<&NativeFunction<'a, Constant, Value>> {
+        self.functions.get(id)
+    }
+
+    pub fn get_id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+impl<'a, Constant, Value: Debug> Default for NativeRegistry<'a, Constant, Value> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::native::{NativeRegistry, MAX_NATIVE_FUNCTIONS};
+    use crate::test_support::{Constant, Value};
+
+    #[test]
+    fn registered_functions_should_get_sequential_ids() {
+        let mut registry: NativeRegistry<Constant, Value> = NativeRegistry::new();
+        let first = registry.register("first", 0, |_, _| Ok(1));
+        let second = registry.register("second", 0, |_, _| Ok(2));
+        assert_eq!(0, first);
+        assert_eq!(1, second);
+        assert_eq!(Some(1), registry.get_id("second"));
+        assert_eq!("first", registry.get(0).unwrap().name);
+    }
+
+    #[test]
+    #[should_panic]
+    fn registering_functions_with_duplicate_names_panics() {
+        let mut registry: NativeRegistry<Constant, Value> = NativeRegistry::new();
+        registry.register("f", 0, |_, _| Ok(1));
+        registry.register("f", 1, |_, _| Ok(2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn registering_more_functions_than_call_native_can_address_panics() {
+        let mut registry: NativeRegistry<Constant, Value> = NativeRegistry::new();
+        for id in 0..MAX_NATIVE_FUNCTIONS {
+            registry.register(&format!("f{}", id), 0, |_, _| Ok(1));
+        }
+        registry.register("one_too_many", 0, |_, _| Ok(1));
+    }
+}
@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::exception::Exception;
+use crate::instruction::Instruction;
+use crate::runtime::call_frame::CallFrame;
+use crate::runtime::instruction_pointer::InstructionPointer;
+use crate::runtime::machine::Machine;
+
+/// Receives events from the dispatch loop of the `Machine`.
+///
+/// Observers are used to build tools such as profilers, tracers and coverage collectors.
+/// All methods do nothing by default. The `ip` passed to instruction callbacks points
+/// to the opcode of the instruction.
+///
+/// Observers are owned by the machine, so to get the collected data back after the execution
+/// register an `Rc<RefCell<YourObserver>>` and keep a clone of it.
+pub trait ExecutionObserver<Constant, Value: Debug> {
+    fn before_instruction(
+        &mut self,
+        _machine: &Machine<Constant, Value>,
+        _ip: &InstructionPointer,
+        _instruction: &Instruction<Constant, Value>,
+    ) {
+    }
+
+    fn after_instruction(
+        &mut self,
+        _machine: &Machine<Constant, Value>,
+        _ip: &InstructionPointer,
+        _instruction: &Instruction<Constant, Value>,
+    ) {
+    }
+
+    fn frame_pushed(&mut self, _machine: &Machine<Constant, Value>, _frame: &CallFrame) {}
+
+    fn frame_discarded(&mut self, _machine: &Machine<Constant, Value>, _frame: &CallFrame) {}
+
+    /// Called for every exception that is raised while running an instruction,
+    /// including the ones that are caught by exception handlers.
+    fn exception_raised(&mut self, _machine: &Machine<Constant, Value>, _exception: &Exception) {}
+
+    /// Called when a global is written with `Machine::set_global`.
+    fn global_written(&mut self, _machine: &Machine<Constant, Value>, _name: &str, _value: &Value) {
+    }
+}
+
+impl<Constant, Value: Debug, T: ExecutionObserver<Constant, Value>>
+    ExecutionObserver<Constant, Value> for Rc<RefCell<T>>
+{
+    fn before_instruction(
+        &mut self,
+        machine: &Machine<Constant, Value>,
+        ip: &InstructionPointer,
+        instruction: &Instruction<Constant, Value>,
+    ) {
+        self.borrow_mut()
+            .before_instruction(machine, ip, instruction)
+    }
+
+    fn after_instruction(
+        &mut self,
+        machine: &Machine<Constant, Value>,
+        ip: &InstructionPointer,
+        instruction: &Instruction<Constant, Value>,
+    ) {
+        self.borrow_mut()
+            .after_instruction(machine, ip, instruction)
+    }
+
+    fn frame_pushed(&mut self, machine: &Machine<Constant, Value>, frame: &CallFrame) {
+        self.borrow_mut().frame_pushed(machine, frame)
+    }
+
+    fn frame_discarded(&mut self, machine: &Machine<Constant, Value>, frame: &CallFrame) {
+        self.borrow_mut().frame_discarded(machine, frame)
+    }
+
+    fn exception_raised(&mut self, machine: &Machine<Constant, Value>, exception: &Exception) {
+        self.borrow_mut().exception_raised(machine, exception)
+    }
+
+    fn global_written(&mut self, machine: &Machine<Constant, Value>, name: &str, value: &Value) {
+        self.borrow_mut().global_written(machine, name, value)
+    }
+}
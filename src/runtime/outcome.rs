@@ -0,0 +1,77 @@
+use crate::exception::Exception;
+use crate::runtime::call_frame::CallFrame;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// The result of a program that finished without exceptions
+#[derive(Debug)]
+pub struct Outcome<Value> {
+    /// The operand that was left on top of the operand stack, if any
+    pub value: Option<Value>,
+}
+
+/// An entry of a stack trace
+///
+/// `Frame` is a snapshot of a `CallFrame` that was active when an exception was raised
+/// and `Native` is a host function that raised the exception.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackTraceEntry {
+    Frame {
+        chunk_id: usize,
+        name: String,
+        instruction_pointer: usize,
+    },
+    Native {
+        name: String,
+    },
+}
+
+impl From<&CallFrame> for StackTraceEntry {
+    fn from(frame: &CallFrame) -> Self {
+        StackTraceEntry::Frame {
+            chunk_id: frame.chunk_id,
+            name: frame.name.clone(),
+            instruction_pointer: frame.instruction_pointer.instruction_pointer,
+        }
+    }
+}
+
+impl Display for StackTraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StackTraceEntry::Frame {
+                chunk_id,
+                name,
+                instruction_pointer,
+            } => write!(f, "{} (#{}:{})", name, chunk_id, instruction_pointer),
+            StackTraceEntry::Native { name } => write!(f, "{} (native)", name),
+        }
+    }
+}
+
+/// An exception that terminated the program
+///
+/// `stack_trace` lists the call frames that were active when the `exception` was raised,
+/// starting from the innermost one.
+#[derive(Debug)]
+pub struct VmError {
+    pub exception: Exception,
+    pub stack_trace: Vec<StackTraceEntry>,
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.exception)?;
+        for entry in &self.stack_trace {
+            write!(f, "\n\tat {}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for VmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.exception)
+    }
+}
@@ -53,7 +53,11 @@ impl<T> Stack<T> {
         self.data.get(index)
     }
 
-    pub fn rev(&self) -> Rev<Iter<T>> {
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn rev(&self) -> Rev<Iter<'_, T>> {
         self.data.iter().rev()
     }
 }
@@ -0,0 +1,153 @@
+//! Instructions and helpers shared by the tests
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::byte_readable::ByteReadable;
+use crate::code::{Chunk, Code};
+use crate::exception::{Exception, ExceptionType};
+use crate::instruction::{Instruction, InstructionFn, RawInstructionFn};
+use crate::instruction_table::InstructionTable;
+use crate::runtime::{ExecutionObserver, InstructionPointer, Machine};
+
+pub type Constant = i32;
+pub type Value = i32;
+
+/// Does nothing, the arguments are skipped
+pub fn noop(_: &mut Machine<Constant, Value>, _: InstructionPointer) -> Result<(), Exception> {
+    Ok(())
+}
+
+/// Pushes the argument byte
+pub fn push(
+    machine: &mut Machine<Constant, Value>,
+    mut args_ip: InstructionPointer,
+) -> Result<(), Exception> {
+    let value = machine.read(&mut args_ip).unwrap();
+    machine.push_operand(i32::from(value));
+    Ok(())
+}
+
+/// Calls the chunk with the id in the argument byte
+pub fn call(
+    machine: &mut Machine<Constant, Value>,
+    mut args_ip: InstructionPointer,
+) -> Result<(), Exception> {
+    let chunk_id = machine.read(&mut args_ip).unwrap();
+    let start_slot = machine.operand_stack_len();
+    machine.push_frame(usize::from(chunk_id), "f".to_string(), start_slot);
+    Ok(())
+}
+
+/// Discards the current frame and returns the operand on top of it, if there is one
+pub fn ret(machine: &mut Machine<Constant, Value>, _: InstructionPointer) -> Result<(), Exception> {
+    let start_slot = machine.peek_frame()?.start_slot;
+    let value = if machine.operand_stack_len() > start_slot {
+        Some(machine.pop_operand()?)
+    } else {
+        None
+    };
+    machine.discard_frame()?;
+    if let Some(value) = value {
+        machine.push_operand(value);
+    }
+    Ok(())
+}
+
+/// Raises a runtime exception named `Thrown`
+pub fn throw(_: &mut Machine<Constant, Value>, _: InstructionPointer) -> Result<(), Exception> {
+    Err(Exception {
+        exception_type: ExceptionType::Runtime,
+        name: "Thrown".to_string(),
+        message: "".to_string(),
+    })
+}
+
+pub fn add(left: i32, right: i32) -> Result<i32, Exception> {
+    Ok(left + right)
+}
+
+/// Jumps forward by the argument byte
+pub fn jump(
+    machine: &mut Machine<Constant, Value>,
+    mut args_ip: InstructionPointer,
+) -> Result<(), Exception> {
+    let offset = machine.read(&mut args_ip).unwrap();
+    machine
+        .instruction_pointer()?
+        .jump_forward(usize::from(offset));
+    Ok(())
+}
+
+pub const fn raw(
+    op_code: u8,
+    name: &'static str,
+    byte_arity: usize,
+    instruction_fn: RawInstructionFn<Constant, Value>,
+) -> Instruction<Constant, Value> {
+    Instruction {
+        op_code,
+        name,
+        instruction_fn: InstructionFn::Raw {
+            byte_arity,
+            instruction_fn,
+        },
+    }
+}
+
+pub const PUSH: Instruction<Constant, Value> = raw(0, "PUSH", 1, push);
+pub const ADD: Instruction<Constant, Value> = Instruction {
+    op_code: 1,
+    name: "ADD",
+    instruction_fn: InstructionFn::BinaryOp(add),
+};
+pub const CALL: Instruction<Constant, Value> = raw(2, "CALL", 1, call);
+pub const CALL_NATIVE: Instruction<Constant, Value> = Instruction {
+    op_code: 3,
+    name: "CALL_NATIVE",
+    instruction_fn: InstructionFn::CallNative,
+};
+pub const RET: Instruction<Constant, Value> = raw(4, "RET", 0, ret);
+pub const THROW: Instruction<Constant, Value> = raw(5, "THROW", 0, throw);
+pub const JUMP: Instruction<Constant, Value> = raw(6, "JUMP", 1, jump);
+
+pub const INSTRUCTIONS: [&Instruction<Constant, Value>; 7] =
+    [&PUSH, &ADD, &CALL, &CALL_NATIVE, &RET, &THROW, &JUMP];
+
+/// Creates code with a chunk without constants for each element of `chunks`.
+pub fn code(chunks: Vec<Vec<u8>>) -> Code<'static, Constant> {
+    Code {
+        chunks: chunks
+            .into_iter()
+            .map(|code| Chunk {
+                constants: vec![],
+                code: code.into(),
+                exception_handlers: vec![],
+            })
+            .collect(),
+    }
+}
+
+/// Creates a machine with `INSTRUCTIONS` that is about to run chunk 0 in the frame `main`.
+pub fn machine<'a>(code: &'a Code<'a, Constant>) -> Machine<'a, Constant, Value> {
+    let mut machine = Machine::new(code, InstructionTable::instructions(&INSTRUCTIONS));
+    machine.push_frame(0, "main".to_string(), 0);
+    machine
+}
+
+/// Runs the chunks to the end with the observer and returns the observer back.
+///
+/// The observer is added before the frame `main` is pushed.
+pub fn run_observed<Observer: ExecutionObserver<Constant, Value>>(
+    chunks: Vec<Vec<u8>>,
+    observer: Observer,
+) -> Observer {
+    let code = code(chunks);
+    let observer = Rc::new(RefCell::new(observer));
+    let mut machine = Machine::new(&code, InstructionTable::instructions(&INSTRUCTIONS));
+    machine.add_observer(Rc::clone(&observer));
+    machine.push_frame(0, "main".to_string(), 0);
+    machine.start().unwrap();
+    drop(machine);
+    Rc::try_unwrap(observer).ok().unwrap().into_inner()
+}
@@ -0,0 +1,106 @@
+use crate::exception::{Exception, ExceptionType};
+
+pub struct UnknownOpCodeAt {
+    pub chunk_id: usize,
+    pub offset: usize,
+    pub op_code: u8,
+}
+
+impl From<UnknownOpCodeAt> for Exception {
+    fn from(exception: UnknownOpCodeAt) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "UnknownOpCodeAt".to_string(),
+            message: format!(
+                "Chunk #{} at {}: unknown opcode {}",
+                exception.chunk_id, exception.offset, exception.op_code
+            ),
+        }
+    }
+}
+
+/// The chunk ended before all arguments of the instruction
+pub struct TruncatedInstruction {
+    pub chunk_id: usize,
+    pub offset: usize,
+    pub name: String,
+    pub byte_arity: usize,
+    pub n_argument_bytes: usize,
+}
+
+impl From<TruncatedInstruction> for Exception {
+    fn from(exception: TruncatedInstruction) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "TruncatedInstruction".to_string(),
+            message: format!(
+                "Chunk #{} at {}: {} expects {} argument bytes but the chunk ends after {}",
+                exception.chunk_id,
+                exception.offset,
+                exception.name,
+                exception.byte_arity,
+                exception.n_argument_bytes
+            ),
+        }
+    }
+}
+
+pub struct ConstantOutOfRange {
+    pub chunk_id: usize,
+    pub offset: usize,
+    pub constant_id: u64,
+    pub n_constants: usize,
+}
+
+impl From<ConstantOutOfRange> for Exception {
+    fn from(exception: ConstantOutOfRange) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "ConstantOutOfRange".to_string(),
+            message: format!(
+                "Chunk #{} at {}: constant #{} does not exist, the chunk has {} constants",
+                exception.chunk_id, exception.offset, exception.constant_id, exception.n_constants
+            ),
+        }
+    }
+}
+
+pub struct ChunkOutOfRange {
+    pub chunk_id: usize,
+    pub offset: usize,
+    pub target_chunk_id: u64,
+    pub n_chunks: usize,
+}
+
+impl From<ChunkOutOfRange> for Exception {
+    fn from(exception: ChunkOutOfRange) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "ChunkOutOfRange".to_string(),
+            message: format!(
+                "Chunk #{} at {}: chunk #{} does not exist, the code has {} chunks",
+                exception.chunk_id, exception.offset, exception.target_chunk_id, exception.n_chunks
+            ),
+        }
+    }
+}
+
+/// A jump does not land at the start of an instruction or at the end of the chunk
+pub struct InvalidJumpTarget {
+    pub chunk_id: usize,
+    pub offset: usize,
+    pub target: i128,
+}
+
+impl From<InvalidJumpTarget> for Exception {
+    fn from(exception: InvalidJumpTarget) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "InvalidJumpTarget".to_string(),
+            message: format!(
+                "Chunk #{} at {}: jump target {} is not the start of an instruction",
+                exception.chunk_id, exception.offset, exception.target
+            ),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub use verifier::{ArgumentKind, Verifier};
+
+pub mod exceptions;
+mod verifier;
@@ -0,0 +1,330 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+use crate::byte_readable::ByteOrder;
+use crate::code::{Chunk, Code};
+use crate::decoder::{decode, DecodedInstruction};
+use crate::exception::Exception;
+use crate::instruction_table::InstructionTable;
+use crate::verification::exceptions::{
+    ChunkOutOfRange, ConstantOutOfRange, InvalidJumpTarget, TruncatedInstruction, UnknownOpCodeAt,
+};
+
+/// The meaning of an instruction argument that occupies the given number of bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind {
+    /// Any value
+    Raw(usize),
+    /// An index into the constants of the current chunk
+    ConstantIndex(usize),
+    /// The id of a chunk
+    ChunkId(usize),
+    /// The distance from the end of the instruction forward to the jump target
+    ForwardJump(usize),
+    /// The distance from the end of the instruction backward to the jump target
+    BackwardJump(usize),
+    /// The offset of the jump target from the start of the chunk
+    JumpTarget(usize),
+}
+
+impl ArgumentKind {
+    pub fn width(self) -> usize {
+        match self {
+            ArgumentKind::Raw(width)
+            | ArgumentKind::ConstantIndex(width)
+            | ArgumentKind::ChunkId(width)
+            | ArgumentKind::ForwardJump(width)
+            | ArgumentKind::BackwardJump(width)
+            | ArgumentKind::JumpTarget(width) => width,
+        }
+    }
+}
+
+/// The chunk whose instructions are verified
+struct ChunkContext<'c, 'b, Constant> {
+    code: &'c Code<'b, Constant>,
+    chunk_id: usize,
+    chunk: &'c Chunk<'b, Constant>,
+    /// Offsets of all instructions and the end of the chunk
+    boundaries: HashSet<usize>,
+}
+
+/// Checks code before it is run.
+///
+/// The verifier reports unknown opcodes and instructions whose arguments are truncated
+/// by the end of the chunk. For instructions with declared `ArgumentKind`s it also checks
+/// that constant indices and chunk ids exist and that jumps land at the start of an instruction
+/// (or at the end of the chunk). Multi-byte arguments are read in the byte order of the verifier.
+pub struct Verifier<'a, Constant, Value: Debug> {
+    instruction_table: &'a InstructionTable<'a, Constant, Value>,
+    arguments: HashMap<u8, Vec<ArgumentKind>>,
+    byte_order: ByteOrder,
+}
+
+impl<'a, Constant, Value: Debug> Verifier<'a, Constant, Value> {
+    pub fn new(
+        instruction_table: &'a InstructionTable<'a, Constant, Value>,
+    ) -> Verifier<'a, Constant, Value> {
+        Verifier {
+            instruction_table,
+            arguments: HashMap::new(),
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    pub fn with_byte_order(self, byte_order: ByteOrder) -> Verifier<'a, Constant, Value> {
+        Verifier { byte_order, ..self }
+    }
+
+    /// Declares the kinds of the arguments of the instruction.
+    ///
+    /// Panics if the instruction is not registered, the widths of the arguments
+    /// do not add up to its `byte_arity` or an argument is wider than 8 bytes.
+    pub fn declare_arguments(&mut self, op_code: u8, arguments: &[ArgumentKind]) {
+        let instruction = match self.instruction_table.get_instruction(op_code) {
+            Some(instruction) => instruction,
+            None => panic!("No instruction with opcode {} is registered", op_code),
+        };
+        let byte_arity = instruction.instruction_fn.byte_arity();
+        let width: usize = arguments.iter().map(|argument| argument.width()).sum();
+        if width != byte_arity {
+            panic!(
+                "Arguments of {} occupy {} bytes but its byte arity is {}",
+                instruction.name, width, byte_arity
+            );
+        }
+        if arguments.iter().any(|argument| argument.width() > 8) {
+            panic!(
+                "Arguments of {} must not be wider than 8 bytes",
+                instruction.name
+            );
+        }
+        self.arguments.insert(op_code, arguments.to_vec());
+    }
+
+    /// Returns all violations that were found in the code.
+    pub fn verify(&self, code: &Code<Constant>) -> Result<(), Vec<Exception>> {
+        let mut violations = vec![];
+        for (chunk_id, chunk) in code.chunks.iter().enumerate() {
+            self.verify_chunk(code, chunk_id, chunk, &mut violations);
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn verify_chunk(
+        &self,
+        code: &Code<Constant>,
+        chunk_id: usize,
+        chunk: &Chunk<Constant>,
+        violations: &mut Vec<Exception>,
+    ) {
+        let instructions = decode(&chunk.code, self.instruction_table);
+        let mut boundaries: HashSet<usize> = instructions
+            .iter()
+            .map(|instruction| instruction.offset)
+            .collect();
+        boundaries.insert(chunk.code.len());
+        let context = ChunkContext {
+            code,
+            chunk_id,
+            chunk,
+            boundaries,
+        };
+        for instruction in &instructions {
+            let definition = match instruction.instruction {
+                Some(definition) => definition,
+                None => {
+                    violations.push(Exception::from(UnknownOpCodeAt {
+                        chunk_id,
+                        offset: instruction.offset,
+                        op_code: instruction.op_code,
+                    }));
+                    continue;
+                }
+            };
+            if instruction.truncated {
+                violations.push(Exception::from(TruncatedInstruction {
+                    chunk_id,
+                    offset: instruction.offset,
+                    name: definition.name.to_string(),
+                    byte_arity: definition.instruction_fn.byte_arity(),
+                    n_argument_bytes: instruction.arguments.len(),
+                }));
+                continue;
+            }
+            if let Some(arguments) = self.arguments.get(&instruction.op_code) {
+                self.verify_arguments(&context, instruction, arguments, violations);
+            }
+        }
+    }
+
+    fn verify_arguments(
+        &self,
+        context: &ChunkContext<Constant>,
+        instruction: &DecodedInstruction<Constant, Value>,
+        arguments: &[ArgumentKind],
+        violations: &mut Vec<Exception>,
+    ) {
+        let ChunkContext {
+            code,
+            chunk_id,
+            chunk,
+            boundaries,
+        } = context;
+        let chunk_id = *chunk_id;
+        let offset = instruction.offset;
+        let end = instruction.end() as i128;
+        let mut bytes = instruction.arguments;
+        for argument in arguments {
+            let (value_bytes, rest) = bytes.split_at(argument.width());
+            bytes = rest;
+            let value = self.read_value(value_bytes);
+            match argument {
+                ArgumentKind::Raw(_) => {}
+                ArgumentKind::ConstantIndex(_) => {
+                    if value >= chunk.constants.len() as u64 {
+                        violations.push(Exception::from(ConstantOutOfRange {
+                            chunk_id,
+                            offset,
+                            constant_id: value,
+                            n_constants: chunk.constants.len(),
+                        }));
+                    }
+                }
+                ArgumentKind::ChunkId(_) => {
+                    if value >= code.chunks.len() as u64 {
+                        violations.push(Exception::from(ChunkOutOfRange {
+                            chunk_id,
+                            offset,
+                            target_chunk_id: value,
+                            n_chunks: code.chunks.len(),
+                        }));
+                    }
+                }
+                ArgumentKind::ForwardJump(_)
+                | ArgumentKind::BackwardJump(_)
+                | ArgumentKind::JumpTarget(_) => {
+                    let target = match argument {
+                        ArgumentKind::ForwardJump(_) => end + i128::from(value),
+                        ArgumentKind::BackwardJump(_) => end - i128::from(value),
+                        _ => i128::from(value),
+                    };
+                    let is_boundary = target >= 0 && boundaries.contains(&(target as usize));
+                    if !is_boundary {
+                        violations.push(Exception::from(InvalidJumpTarget {
+                            chunk_id,
+                            offset,
+                            target,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_value(&self, bytes: &[u8]) -> u64 {
+        let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+        match self.byte_order {
+            ByteOrder::LittleEndian => bytes.iter().rev().fold(0, fold),
+            ByteOrder::BigEndian => bytes.iter().fold(0, fold),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::code::{Chunk, Code};
+    use crate::exception::ExceptionType;
+    use crate::instruction::Instruction;
+    use crate::instruction_table::InstructionTable;
+    use crate::test_support::{code, noop, raw, Constant, Value};
+    use crate::verification::verifier::{ArgumentKind, Verifier};
+
+    const CONST: Instruction<Constant, Value> = raw(0, "CONST", 2, noop);
+    const CALL: Instruction<Constant, Value> = raw(1, "CALL", 1, noop);
+    const JUMP: Instruction<Constant, Value> = raw(2, "JUMP", 1, noop);
+    const LOOP: Instruction<Constant, Value> = raw(3, "LOOP", 1, noop);
+
+    const INSTRUCTIONS: [&Instruction<Constant, Value>; 4] = [&CONST, &CALL, &JUMP, &LOOP];
+
+    fn verify(chunks: Vec<Vec<u8>>) -> Result<(), Vec<String>> {
+        let code = Code {
+            chunks: chunks
+                .into_iter()
+                .map(|code| Chunk {
+                    constants: vec![10, 20],
+                    code: code.into(),
+                    exception_handlers: vec![],
+                })
+                .collect(),
+        };
+        let table = InstructionTable::instructions(&INSTRUCTIONS);
+        let mut verifier = Verifier::new(&table);
+        verifier.declare_arguments(0, &[ArgumentKind::ConstantIndex(2)]);
+        verifier.declare_arguments(1, &[ArgumentKind::ChunkId(1)]);
+        verifier.declare_arguments(2, &[ArgumentKind::ForwardJump(1)]);
+        verifier.declare_arguments(3, &[ArgumentKind::BackwardJump(1)]);
+        verifier.verify(&code).map_err(|violations| {
+            violations
+                .into_iter()
+                .inspect(|violation| {
+                    assert!(matches!(violation.exception_type, ExceptionType::Static))
+                })
+                .map(|violation| violation.name)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn valid_code_should_pass() {
+        // CONST 1; JUMP 2; CALL 1; LOOP 9
+        let result = verify(vec![vec![0, 1, 0, 2, 2, 1, 1, 3, 9], vec![]]);
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn all_violations_should_be_reported() {
+        // CONST 2; CALL 5; JUMP 1; LOOP 3; ??? 42; JUMP (truncated)
+        let result = verify(vec![vec![0, 2, 0], vec![1, 5, 2, 1, 3, 3, 42, 2]]);
+        assert_eq!(
+            Err(vec![
+                "ConstantOutOfRange".to_string(),
+                "ChunkOutOfRange".to_string(),
+                "InvalidJumpTarget".to_string(),
+                "InvalidJumpTarget".to_string(),
+                "UnknownOpCodeAt".to_string(),
+                "TruncatedInstruction".to_string(),
+            ]),
+            result
+        );
+    }
+
+    #[test]
+    fn absolute_jump_targets_should_be_checked() {
+        let table = InstructionTable::instructions(&INSTRUCTIONS);
+        let mut verifier = Verifier::new(&table);
+        verifier.declare_arguments(2, &[ArgumentKind::JumpTarget(1)]);
+        // JUMP @4; JUMP @0; JUMP @6
+        let valid = code(vec![vec![2, 4, 2, 0, 2, 6]]);
+        assert!(verifier.verify(&valid).is_ok());
+        // JUMP @1; JUMP @7
+        let invalid = code(vec![vec![2, 1, 2, 7]]);
+        let violations = verifier.verify(&invalid).unwrap_err();
+        assert_eq!(2, violations.len());
+        assert!(violations
+            .iter()
+            .all(|violation| violation.name == "InvalidJumpTarget"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn declaring_arguments_that_do_not_match_byte_arity_panics() {
+        let table = InstructionTable::instructions(&INSTRUCTIONS);
+        let mut verifier = Verifier::new(&table);
+        verifier.declare_arguments(0, &[ArgumentKind::ConstantIndex(1)]);
+    }
+}
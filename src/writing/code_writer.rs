@@ -0,0 +1,365 @@
+use crate::byte_readable::ByteOrder;
+use crate::code::{Chunk, Code};
+use crate::exception::Exception;
+use crate::parsing::exceptions::EmptyCode;
+use crate::parsing::{ChunkLayout, CodeHeader};
+use crate::writing::constant_serializer::ConstantSerializerTable;
+use crate::writing::exceptions::{
+    ChunkWritingError, CodeTooLong, TooManyConstants, UnserializableConstant,
+    UnserializableExceptionHandlers,
+};
+
+/// Serializes code to raw bytes that can be read by `CodeParser`.
+///
+/// If a header is set, it is written before the chunks.
+/// Chunks are written using the `ChunkLayout` of the writer, which is `Compact` by default,
+/// and their sizes are written in the byte order of the writer, which is little-endian by default.
+/// Exception handlers are not part of the binary format, chunks that have them cannot be written.
+pub struct CodeWriter<'a, Constant> {
+    serializers: &'a ConstantSerializerTable<'a, Constant>,
+    header: Option<CodeHeader>,
+    layout: ChunkLayout,
+    byte_order: ByteOrder,
+}
+
+impl<'a, Constant> CodeWriter<'a, Constant> {
+    pub fn new(serializers: &'a ConstantSerializerTable<'a, Constant>) -> CodeWriter<'a, Constant> {
+        CodeWriter {
+            serializers,
+            header: None,
+            layout: ChunkLayout::default(),
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+
+    pub fn with_byte_order(self, byte_order: ByteOrder) -> CodeWriter<'a, Constant> {
+        CodeWriter { byte_order, ..self }
+    }
+
+    pub fn with_layout(self, layout: ChunkLayout) -> CodeWriter<'a, Constant> {
+        CodeWriter { layout, ..self }
+    }
+
+    pub fn with_header(self, header: CodeHeader) -> CodeWriter<'a, Constant> {
+        CodeWriter {
+            header: Some(header),
+            ..self
+        }
+    }
+
+    pub fn write(&self, code: &Code<Constant>) -> Result<Vec<u8>, Exception> {
+        if code.chunks.is_empty() {
+            return Err(Exception::from(EmptyCode));
+        }
+        let mut bytes: Vec<u8> = vec![];
+        if let Some(header) = &self.header {
+            header.write(self.layout, &mut bytes);
+        }
+        for (chunk_id, chunk) in code.chunks.iter().enumerate() {
+            self.write_chunk(chunk, &mut bytes)
+                .map_err(|err| ChunkWritingError(chunk_id, err))?;
+        }
+        Ok(bytes)
+    }
+
+    fn write_chunk(&self, chunk: &Chunk<Constant>, bytes: &mut Vec<u8>) -> Result<(), Exception> {
+        if !chunk.exception_handlers.is_empty() {
+            return Err(Exception::from(UnserializableExceptionHandlers(
+                chunk.exception_handlers.len(),
+            )));
+        }
+        let n_constants = chunk.constants.len();
+        if n_constants > self.layout.max_constants() {
+            return Err(Exception::from(TooManyConstants(
+                n_constants,
+                self.layout.max_constants(),
+            )));
+        }
+        let n_code_bytes = chunk.code.len();
+        if n_code_bytes > self.layout.max_code_bytes() {
+            return Err(Exception::from(CodeTooLong(
+                n_code_bytes,
+                self.layout.max_code_bytes(),
+            )));
+        }
+        match self.layout {
+            ChunkLayout::Compact => bytes.push(n_constants as u8),
+            ChunkLayout::Wide => self.write_u32(n_constants as u32, bytes),
+        }
+        for (constant_id, constant) in chunk.constants.iter().enumerate() {
+            let (constant_type, data) = self
+                .serializers
+                .serialize(constant, self.byte_order)
+                .ok_or(UnserializableConstant(constant_id))?;
+            bytes.push(constant_type);
+            bytes.extend(data);
+        }
+        match self.layout {
+            ChunkLayout::Compact => self.write_u16(n_code_bytes as u16, bytes),
+            ChunkLayout::Wide => self.write_u32(n_code_bytes as u32, bytes),
+        }
+        bytes.extend_from_slice(&chunk.code);
+        Ok(())
+    }
+
+    fn write_u16(&self, value: u16, bytes: &mut Vec<u8>) {
+        match self.byte_order {
+            ByteOrder::LittleEndian => bytes.extend(&value.to_le_bytes()),
+            ByteOrder::BigEndian => bytes.extend(&value.to_be_bytes()),
+        }
+    }
+
+    fn write_u32(&self, value: u32, bytes: &mut Vec<u8>) {
+        match self.byte_order {
+            ByteOrder::LittleEndian => bytes.extend(&value.to_le_bytes()),
+            ByteOrder::BigEndian => bytes.extend(&value.to_be_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_readable::{ByteOrder, ByteReadable};
+    use crate::code::{Chunk, Code};
+    use crate::exception::Exception;
+    use crate::parsing::{
+        ChunkLayout, CodeHeader, CodeParser, ConstantParser, ConstantParserTable, RawBytes,
+        RawBytesPointer,
+    };
+    use crate::writing::code_writer::CodeWriter;
+    use crate::writing::constant_serializer::{ConstantSerializer, ConstantSerializerTable};
+    use crate::ExceptionHandler;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Constant {
+        Int(i32),
+        Bool(bool),
+    }
+
+    fn parse_int(bytes: &RawBytes, pointer: &mut RawBytesPointer) -> Result<Constant, Exception> {
+        Ok(Constant::Int(bytes.read_i32(pointer).unwrap()))
+    }
+
+    fn parse_bool(bytes: &RawBytes, pointer: &mut RawBytesPointer) -> Result<Constant, Exception> {
+        Ok(Constant::Bool(bytes.read(pointer).unwrap() != 0))
+    }
+
+    fn serialize_int(constant: &Constant, byte_order: ByteOrder) -> Option<Vec<u8>> {
+        if let Constant::Int(value) = constant {
+            match byte_order {
+                ByteOrder::LittleEndian => Some(value.to_le_bytes().to_vec()),
+                ByteOrder::BigEndian => Some(value.to_be_bytes().to_vec()),
+            }
+        } else {
+            None
+        }
+    }
+
+    fn serialize_bool(constant: &Constant, _: ByteOrder) -> Option<Vec<u8>> {
+        if let Constant::Bool(value) = constant {
+            Some(vec![u8::from(*value)])
+        } else {
+            None
+        }
+    }
+
+    const PARSERS: [ConstantParser<Constant>; 2] = [
+        ConstantParser {
+            constant_type: 0,
+            parser_fn: parse_int,
+        },
+        ConstantParser {
+            constant_type: 1,
+            parser_fn: parse_bool,
+        },
+    ];
+
+    const SERIALIZERS: [ConstantSerializer<Constant>; 2] = [
+        ConstantSerializer {
+            constant_type: 0,
+            serializer_fn: serialize_int,
+        },
+        ConstantSerializer {
+            constant_type: 1,
+            serializer_fn: serialize_bool,
+        },
+    ];
+
+    fn write(code: &Code<Constant>) -> Result<Vec<u8>, Exception> {
+        let table = ConstantSerializerTable::serializers(&SERIALIZERS);
+        CodeWriter::new(&table).write(code)
+    }
+
+    fn round_trip(code: &Code<Constant>) -> Code<'static, Constant> {
+        let bytes = write(code).unwrap();
+        let table = ConstantParserTable::parsers(&PARSERS);
+        CodeParser::new(&table)
+            .parse(&RawBytes::from_bytes(bytes))
+            .unwrap()
+    }
+
+    fn assert_code_eq(expected: &Code<Constant>, actual: &Code<Constant>) {
+        assert_eq!(expected.chunks.len(), actual.chunks.len());
+        for (expected, actual) in expected.chunks.iter().zip(&actual.chunks) {
+            assert_eq!(expected.constants, actual.constants);
+            assert_eq!(expected.code, actual.code);
+        }
+    }
+
+    #[test]
+    fn writer_should_use_parser_layout() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: vec![Constant::Bool(true)],
+                code: vec![4, 5, 6].into(),
+                exception_handlers: vec![],
+            }],
+        };
+        assert_eq!(vec![1, 1, 1, 3, 0, 4, 5, 6], write(&code).unwrap());
+    }
+
+    #[test]
+    fn written_code_should_be_parsed_back() {
+        let code = Code {
+            chunks: vec![
+                Chunk {
+                    constants: vec![Constant::Int(-1), Constant::Bool(true), Constant::Int(300)],
+                    code: vec![1, 2, 3].into(),
+                    exception_handlers: vec![],
+                },
+                Chunk {
+                    constants: vec![],
+                    code: vec![].into(),
+                    exception_handlers: vec![],
+                },
+                Chunk {
+                    constants: vec![Constant::Bool(false)],
+                    code: (0..1000).map(|i| (i % 256) as u8).collect(),
+                    exception_handlers: vec![],
+                },
+            ],
+        };
+        assert_code_eq(&code, &round_trip(&code));
+    }
+
+    #[test]
+    fn writing_empty_code_should_fail() {
+        let code = Code { chunks: vec![] };
+        assert_eq!("EmptyCode", write(&code).unwrap_err().name);
+    }
+
+    #[test]
+    fn writing_too_many_constants_should_fail() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: vec![Constant::Bool(true); 256],
+                code: vec![].into(),
+                exception_handlers: vec![],
+            }],
+        };
+        let error = write(&code).unwrap_err();
+        assert_eq!("ChunkWritingError", error.name);
+        assert!(error.message.contains("256 constants"));
+    }
+
+    #[test]
+    fn writing_too_long_code_should_fail() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: vec![],
+                code: vec![0; 70000].into(),
+                exception_handlers: vec![],
+            }],
+        };
+        assert!(write(&code).unwrap_err().message.contains("70000 bytes"));
+    }
+
+    #[test]
+    fn writing_exception_handlers_should_fail() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: vec![],
+                code: vec![0; 4].into(),
+                exception_handlers: vec![ExceptionHandler {
+                    start: 0,
+                    end: 2,
+                    handler: 2,
+                    start_slot: 0,
+                }],
+            }],
+        };
+        let error = write(&code).unwrap_err();
+        assert!(error.message.contains("1 exception handlers"));
+    }
+
+    #[test]
+    fn code_with_header_should_be_parsed_back() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: vec![Constant::Int(5)],
+                code: vec![1, 2].into(),
+                exception_handlers: vec![],
+            }],
+        };
+        let header = CodeHeader::new(42, 1);
+        let serializers = ConstantSerializerTable::serializers(&SERIALIZERS);
+        let bytes = CodeWriter::new(&serializers)
+            .with_header(header)
+            .write(&code)
+            .unwrap();
+        let parsers = ConstantParserTable::parsers(&PARSERS);
+        let parsed = CodeParser::new(&parsers)
+            .with_header(header)
+            .parse(&RawBytes::from_bytes(bytes))
+            .unwrap();
+        assert_code_eq(&code, &parsed);
+    }
+
+    #[test]
+    fn wide_layout_should_lift_chunk_limits() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: (0..300).map(Constant::Int).collect(),
+                code: (0..70000)
+                    .map(|i| (i % 256) as u8)
+                    .collect::<Vec<u8>>()
+                    .into(),
+                exception_handlers: vec![],
+            }],
+        };
+        let header = CodeHeader::new(42, 1);
+        let serializers = ConstantSerializerTable::serializers(&SERIALIZERS);
+        let bytes = CodeWriter::new(&serializers)
+            .with_header(header)
+            .with_layout(ChunkLayout::Wide)
+            .write(&code)
+            .unwrap();
+        let parsers = ConstantParserTable::parsers(&PARSERS);
+        let parsed = CodeParser::new(&parsers)
+            .with_header(header)
+            .parse(&RawBytes::from_bytes(bytes))
+            .unwrap();
+        assert_code_eq(&code, &parsed);
+    }
+
+    #[test]
+    fn big_endian_code_should_be_parsed_back() {
+        let code = Code {
+            chunks: vec![Chunk {
+                constants: vec![Constant::Int(0x01020304)],
+                code: vec![0; 258].into(),
+                exception_handlers: vec![],
+            }],
+        };
+        let serializers = ConstantSerializerTable::serializers(&SERIALIZERS);
+        let bytes = CodeWriter::new(&serializers)
+            .with_byte_order(ByteOrder::BigEndian)
+            .write(&code)
+            .unwrap();
+        assert_eq!(&[1, 0, 1, 2, 3, 4, 1, 2], &bytes[..8]);
+        let parsers = ConstantParserTable::parsers(&PARSERS);
+        let raw_bytes = RawBytes::from_bytes(bytes).with_byte_order(ByteOrder::BigEndian);
+        let parsed = CodeParser::new(&parsers).parse(&raw_bytes).unwrap();
+        assert_code_eq(&code, &parsed);
+    }
+}
@@ -0,0 +1,38 @@
+use crate::byte_readable::ByteOrder;
+
+/// Defines how bytecode constants are serialized, the counterpart of `ConstantParser`.
+///
+/// `serializer_fn` encodes the value of the constant without the leading `constant_type` byte,
+/// so that the bytes can be read back by the `ConstantParser` with the same `constant_type`.
+/// Multi-byte values should be written in the given byte order, the one the code is parsed with.
+/// It should return `None` if the constant is not of this type.
+pub struct ConstantSerializer<Constant> {
+    pub constant_type: u8,
+    pub serializer_fn: ConstantSerializerFn<Constant>,
+}
+
+/// A function that encodes the value of the constant in the given byte order.
+pub type ConstantSerializerFn<Constant> = fn(&Constant, ByteOrder) -> Option<Vec<u8>>;
+
+/// A set of constant serializers
+pub struct ConstantSerializerTable<'a, Constant> {
+    serializers: &'a [ConstantSerializer<Constant>],
+}
+
+impl<'a, Constant> ConstantSerializerTable<'a, Constant> {
+    pub fn serializers(
+        serializers: &'a [ConstantSerializer<Constant>],
+    ) -> ConstantSerializerTable<'a, Constant> {
+        ConstantSerializerTable { serializers }
+    }
+
+    /// Encodes the constant with the first serializer that accepts it.
+    ///
+    /// Returns the type of the constant and the encoded value.
+    pub fn serialize(&self, constant: &Constant, byte_order: ByteOrder) -> Option<(u8, Vec<u8>)> {
+        self.serializers.iter().find_map(|serializer| {
+            (serializer.serializer_fn)(constant, byte_order)
+                .map(|bytes| (serializer.constant_type, bytes))
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use crate::exception::{Exception, ExceptionType};
+
+pub struct ChunkWritingError(pub usize, pub Exception);
+
+impl From<ChunkWritingError> for Exception {
+    fn from(error: ChunkWritingError) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "ChunkWritingError".to_string(),
+            message: format!("Could not write chunk #{}: {}", error.0, error.1),
+        }
+    }
+}
+
+/// The number of constants in a chunk and the maximum supported by the chunk layout
+pub struct TooManyConstants(pub usize, pub usize);
+
+impl From<TooManyConstants> for Exception {
+    fn from(exception: TooManyConstants) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "TooManyConstants".to_string(),
+            message: format!(
+                "Chunk has {} constants but at most {} are supported",
+                exception.0, exception.1
+            ),
+        }
+    }
+}
+
+/// The number of code bytes in a chunk and the maximum supported by the chunk layout
+pub struct CodeTooLong(pub usize, pub usize);
+
+impl From<CodeTooLong> for Exception {
+    fn from(exception: CodeTooLong) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "CodeTooLong".to_string(),
+            message: format!(
+                "Chunk has {} bytes of code but at most {} are supported",
+                exception.0, exception.1
+            ),
+        }
+    }
+}
+
+pub struct UnserializableConstant(pub usize);
+
+impl From<UnserializableConstant> for Exception {
+    fn from(exception: UnserializableConstant) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "UnserializableConstant".to_string(),
+            message: format!("No serializer accepts constant #{}", exception.0),
+        }
+    }
+}
+
+/// The number of exception handlers in a chunk, they are not part of the binary format
+pub struct UnserializableExceptionHandlers(pub usize);
+
+impl From<UnserializableExceptionHandlers> for Exception {
+    fn from(exception: UnserializableExceptionHandlers) -> Self {
+        Exception {
+            exception_type: ExceptionType::Static,
+            name: "UnserializableExceptionHandlers".to_string(),
+            message: format!(
+                "Chunk has {} exception handlers but the binary format cannot store them",
+                exception.0
+            ),
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub use code_writer::CodeWriter;
+pub use constant_serializer::{ConstantSerializer, ConstantSerializerFn, ConstantSerializerTable};
+
+mod code_writer;
+mod constant_serializer;
+pub mod exceptions;